@@ -1,3 +1,62 @@
+use std::fmt::Write;
+use std::path::Path;
+
+// Must match `encoder::MAX_ARGUMENTS`.
+const MAX_PARTS: usize = 32;
+
 fn main() {
-    cc::Build::new().file("wrapper.c").compile("wrapper");
+    // The wrappers need <os/log.h>, which only exists on Apple platforms, and
+    // aren't needed when the argument buffer is encoded in Rust.
+    let apple = std::env::var("CARGO_CFG_TARGET_VENDOR").as_deref() == Ok("apple");
+    let rust_encoder = std::env::var_os("CARGO_FEATURE_RUST_ENCODER").is_some();
+
+    if apple && !rust_encoder {
+        cc::Build::new().file("wrapper.c").compile("wrapper");
+    }
+
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    std::fs::write(Path::new(&out_dir).join("formats.rs"), formats()).unwrap();
+}
+
+/// Messages with per-part privacy are logged as alternating public and
+/// private strings. The log only accepts format strings which are constants in
+/// the binary, so one is generated for each length and starting privacy.
+fn formats() -> String {
+    let mut code = String::new();
+    let mut table = String::new();
+
+    for first_private in [false, true] {
+        table.push_str("    [\n");
+        for len in 1..=MAX_PARTS {
+            let mut format = String::new();
+            for i in 0..len {
+                let private = first_private ^ (i % 2 == 1);
+                format.push_str(if private { "%{private}s" } else { "%{public}s" });
+            }
+
+            let name = format!("FORMAT_{}_{}", first_private as u8, len);
+            writeln!(
+                code,
+                "#[link_section = \"__TEXT,__oslogstring,cstring_literals\"]\n\
+                 static {}: [u8; {}] = *b\"{}\\0\";",
+                name,
+                format.len() + 1,
+                format
+            )
+            .unwrap();
+            writeln!(table, "        &{},", name).unwrap();
+        }
+        table.push_str("    ],\n");
+    }
+
+    writeln!(
+        code,
+        "/// Indexed by whether the first part is private, then the number of parts\n\
+         /// minus one.\n\
+         static FORMATS: [[&[u8]; {}]; 2] = [\n{}];",
+        MAX_PARTS, table
+    )
+    .unwrap();
+
+    code
 }
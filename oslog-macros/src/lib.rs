@@ -0,0 +1,518 @@
+//! Procedural macros re-exported by the `oslog` crate. See its documentation.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Literal, Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{Expr, LitStr, Token};
+
+mod value;
+
+/// Logs a message whose format string is checked at compile time and stored
+/// as a constant, so Console can group and search entries by it.
+///
+/// ```
+/// use oslog::{os_log_static, Level, OsLog};
+///
+/// let log = OsLog::new("com.example.test", "Sync");
+/// let (items, elapsed, host) = (12u32, 3.5f64, "example.com");
+/// os_log_static!(log, Level::Info, "synced {:u} items in {:f}s from {}", items, elapsed, public(host));
+/// ```
+///
+/// Placeholders are typed:
+///
+/// - `{}` is any `Display` value, logged as a string. Control characters and
+///   nul bytes in it are handled by the log's `ControlChars` and `NulPolicy`,
+///   and the message is dropped if the policy rejects them.
+/// - `{:d}` converts to `i64` with `From`.
+/// - `{:u}` and `{:x}` convert to `u64` with `From`, the latter shown as hex.
+/// - `{:f}` converts to `f64` with `From`.
+///
+/// Arguments can be wrapped in `public(..)` or `private(..)`. Otherwise
+/// strings are private and numbers are public, as in the unified log. Unknown
+/// placeholders, a mismatched number of arguments, and arguments which don't
+/// convert to the placeholder's type are compile errors.
+///
+/// Each argument's privacy is fixed in the format string, so unlike `os_log!`
+/// the log's `HashMask` and default privacy don't apply: private arguments
+/// are logged as private rather than hashed, and the rest of the message is
+/// public even in a category made private with
+/// `Config::with_category_privacy`. Messages aren't split by
+/// `OsLog::with_chunk_size` either.
+#[proc_macro]
+pub fn os_log_static(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as StaticLog);
+    match input.expand() {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Implements `oslog::Sensitive`, and `Debug` in terms of it, so a value can
+/// be logged with `{:?}` without its sensitive fields appearing in the clear.
+///
+/// ```
+/// use oslog::{Level, OsLog, OsLogValue};
+///
+/// #[derive(OsLogValue)]
+/// struct User {
+///     name: String,
+///     #[oslog(private)]
+///     email: String,
+///     #[oslog(hash)]
+///     account: u64,
+///     #[oslog(skip)]
+///     token: String,
+/// }
+///
+/// let log = OsLog::new("com.example.test", "Users");
+/// let user = User {
+///     name: "alice".into(),
+///     email: "alice@example.com".into(),
+///     account: 42,
+///     token: "secret".into(),
+/// };
+/// log.log_fmt(Level::Info, format_args!("signed in {:?}", user));
+/// ```
+///
+/// Fields are formatted with `Debug`, and can be marked:
+///
+/// - `#[oslog(private)]` wraps the field in `oslog::Private`.
+/// - `#[oslog(hash)]` wraps the field in `oslog::Hashed`, logging a salted
+///   hash of it.
+/// - `#[oslog(skip)]` leaves the field out, shown as `..`.
+///
+/// Use it instead of `#[derive(Debug)]`. Structs and enums are supported.
+#[proc_macro_derive(OsLogValue, attributes(oslog))]
+pub fn derive_os_log_value(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+    match value::expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Spec {
+    Display,
+    Signed,
+    Unsigned,
+    Hex,
+    Float,
+}
+
+impl Spec {
+    fn conversion(self) -> &'static str {
+        match self {
+            Self::Display => "s",
+            Self::Signed => "lld",
+            Self::Unsigned => "llu",
+            Self::Hex => "llx",
+            Self::Float => "f",
+        }
+    }
+
+    /// Matches the unified log's defaults: strings are private, scalars are
+    /// public.
+    fn default_private(self) -> bool {
+        self == Self::Display
+    }
+}
+
+/// The literal text between placeholders, and the placeholders themselves.
+#[derive(Debug, PartialEq, Eq)]
+struct Format {
+    pieces: Vec<String>,
+    specs: Vec<Spec>,
+}
+
+fn parse_format(format: &str) -> Result<Format, String> {
+    if format.contains('\0') {
+        return Err(String::from("format strings can't contain nul bytes"));
+    }
+
+    let mut pieces = vec![String::new()];
+    let mut specs = Vec::new();
+    let mut chars = format.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                pieces.last_mut().unwrap().push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                pieces.last_mut().unwrap().push('}');
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => return Err(String::from("unterminated `{` in format string")),
+                    }
+                }
+
+                specs.push(match placeholder.as_str() {
+                    "" => Spec::Display,
+                    ":d" => Spec::Signed,
+                    ":u" => Spec::Unsigned,
+                    ":x" => Spec::Hex,
+                    ":f" => Spec::Float,
+                    other => {
+                        return Err(format!(
+                            "unsupported placeholder `{{{}}}`, expected one of `{{}}`, `{{:d}}`, `{{:u}}`, `{{:x}}` or `{{:f}}`",
+                            other
+                        ))
+                    }
+                });
+                pieces.push(String::new());
+            }
+            '}' => return Err(String::from("unmatched `}` in format string")),
+            c => pieces.last_mut().unwrap().push(c),
+        }
+    }
+
+    Ok(Format { pieces, specs })
+}
+
+/// Builds the printf style format string the unified log expects, with an
+/// explicit privacy for every argument.
+fn os_log_format(format: &Format, private: &[bool]) -> String {
+    let mut os_log = String::new();
+    for (i, piece) in format.pieces.iter().enumerate() {
+        os_log.push_str(&piece.replace('%', "%%"));
+        if let Some(spec) = format.specs.get(i) {
+            let privacy = if private[i] { "private" } else { "public" };
+            os_log.push_str(&format!("%{{{}}}{}", privacy, spec.conversion()));
+        }
+    }
+    os_log
+}
+
+struct Argument {
+    expr: Expr,
+    private: Option<bool>,
+}
+
+impl Parse for Argument {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let expr: Expr = input.parse()?;
+
+        if let Expr::Call(call) = &expr {
+            if let (Expr::Path(path), 1) = (&*call.func, call.args.len()) {
+                let private = if path.path.is_ident("public") {
+                    Some(false)
+                } else if path.path.is_ident("private") {
+                    Some(true)
+                } else {
+                    None
+                };
+
+                if private.is_some() {
+                    return Ok(Self {
+                        expr: call.args[0].clone(),
+                        private,
+                    });
+                }
+            }
+        }
+
+        Ok(Self {
+            expr,
+            private: None,
+        })
+    }
+}
+
+struct StaticLog {
+    log: Expr,
+    level: Expr,
+    format: LitStr,
+    args: Vec<Argument>,
+}
+
+impl Parse for StaticLog {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let log = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let level = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let format = input.parse()?;
+
+        let args = if input.is_empty() {
+            Vec::new()
+        } else {
+            input.parse::<Token![,]>()?;
+            Punctuated::<Argument, Token![,]>::parse_terminated(input)?
+                .into_iter()
+                .collect()
+        };
+
+        Ok(Self {
+            log,
+            level,
+            format,
+            args,
+        })
+    }
+}
+
+impl StaticLog {
+    fn expand(self) -> syn::Result<TokenStream2> {
+        let span = self.format.span();
+        let format = parse_format(&self.format.value()).map_err(|e| syn::Error::new(span, e))?;
+
+        if format.specs.len() != self.args.len() {
+            return Err(syn::Error::new(
+                span,
+                format!(
+                    "format string has {} placeholder(s) but {} argument(s) were given",
+                    format.specs.len(),
+                    self.args.len()
+                ),
+            ));
+        }
+
+        let private: Vec<bool> = format
+            .specs
+            .iter()
+            .zip(&self.args)
+            .map(|(spec, arg)| arg.private.unwrap_or_else(|| spec.default_private()))
+            .collect();
+
+        let mut os_log = os_log_format(&format, &private).into_bytes();
+        os_log.push(0);
+        let os_log_len = os_log.len();
+        let os_log = Literal::byte_string(&os_log);
+
+        let pieces = &format.pieces;
+
+        // Mixed site spans keep these from shadowing, or being shadowed by,
+        // the caller's variables in the argument expressions. Items like the
+        // static only get call site hygiene, so it's given an unlikely name.
+        let log = format_ident!("log", span = Span::mixed_site());
+        let level = format_ident!("level", span = Span::mixed_site());
+        let format_ident = format_ident!("format", span = Span::mixed_site());
+        let format_static = format_ident!("__OSLOG_FORMAT", span = Span::mixed_site());
+        let (log_expr, level_expr) = (&self.log, &self.level);
+
+        let mut bindings = Vec::new();
+        let mut strings = Vec::new();
+        let mut arguments = Vec::new();
+        for (i, ((spec, arg), private)) in format
+            .specs
+            .iter()
+            .zip(&self.args)
+            .zip(&private)
+            .enumerate()
+        {
+            let expr = &arg.expr;
+            let name = format_ident!("__oslog_arg{}", i, span = Span::mixed_site());
+            let privacy = if *private {
+                quote!(::oslog::Privacy::Private)
+            } else {
+                quote!(::oslog::Privacy::Public)
+            };
+
+            let value = match spec {
+                Spec::Display => {
+                    bindings.push(quote! {
+                        let #name = ::oslog::__private::to_cstring(#log, &#expr);
+                    });
+                    strings.push(name.clone());
+                    quote!(::oslog::backend::Argument::Str(&#name))
+                }
+                Spec::Signed => quote!(::oslog::backend::Argument::Signed(
+                    <i64 as ::core::convert::From<_>>::from(#expr)
+                )),
+                Spec::Unsigned => quote!(::oslog::backend::Argument::Unsigned(
+                    <u64 as ::core::convert::From<_>>::from(#expr)
+                )),
+                Spec::Hex => quote!(::oslog::backend::Argument::Hex(
+                    <u64 as ::core::convert::From<_>>::from(#expr)
+                )),
+                Spec::Float => quote!(::oslog::backend::Argument::Float(
+                    <f64 as ::core::convert::From<_>>::from(#expr)
+                )),
+            };
+            arguments.push(quote!((#privacy, #value)));
+        }
+
+        let mut log_static = quote! {
+            #log.with_static_format(#level, &#format_ident, &[#(#arguments),*]);
+        };
+        // Messages are dropped if the log's `NulPolicy` rejects an argument.
+        if !strings.is_empty() {
+            log_static = quote! {
+                if let (#(::core::option::Option::Some(#strings),)*) = (#(#strings,)*) {
+                    #log_static
+                }
+            };
+        }
+
+        Ok(quote! {{
+            // The unified log stores format strings as offsets into the binary,
+            // so this is placed where clang puts them.
+            #[cfg_attr(
+                target_vendor = "apple",
+                link_section = "__TEXT,__oslogstring,cstring_literals"
+            )]
+            static #format_static: [u8; #os_log_len] = *#os_log;
+
+            let #log = &#log_expr;
+            let #level: ::oslog::Level = #level_expr;
+            if #log.level_is_enabled(#level) {
+                #(#bindings)*
+                #[allow(unsafe_code)]
+                let #format_ident = ::oslog::backend::StaticFormat {
+                    // Safety: generated with exactly one, trailing, nul byte.
+                    format: unsafe {
+                        ::core::ffi::CStr::from_bytes_with_nul_unchecked(&#format_static)
+                    },
+                    pieces: &[#(#pieces),*],
+                };
+                #log_static
+            }
+        }})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!(
+            parse_format("{} has {:d} items, {:u} {:x} {:f} {{}}").unwrap(),
+            Format {
+                pieces: vec![
+                    "".into(),
+                    " has ".into(),
+                    " items, ".into(),
+                    " ".into(),
+                    " ".into(),
+                    " {}".into()
+                ],
+                specs: vec![
+                    Spec::Display,
+                    Spec::Signed,
+                    Spec::Unsigned,
+                    Spec::Hex,
+                    Spec::Float
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_format_errors() {
+        assert!(parse_format("{:?}")
+            .unwrap_err()
+            .contains("unsupported placeholder"));
+        assert!(parse_format("{").unwrap_err().contains("unterminated"));
+        assert!(parse_format("}").unwrap_err().contains("unmatched"));
+        assert!(parse_format("a\0b").unwrap_err().contains("nul"));
+    }
+
+    #[test]
+    fn test_os_log_format() {
+        let format = parse_format("100% of {} took {:f}ms").unwrap();
+        assert_eq!(
+            os_log_format(&format, &[true, false]),
+            "100%% of %{private}s took %{public}fms"
+        );
+    }
+
+    #[test]
+    fn test_argument_count_mismatch() {
+        let input: StaticLog = syn::parse_quote!(log, Level::Info, "{} {}", a);
+        let err = input.expand().unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("2 placeholder(s) but 1 argument(s)"));
+    }
+
+    #[test]
+    fn test_privacy_markers() {
+        let input: StaticLog = syn::parse_quote!(log, Level::Info, "{}", public(name),);
+        assert_eq!(input.args.len(), 1);
+        assert_eq!(input.args[0].private, Some(false));
+
+        let input: StaticLog = syn::parse_quote!(log, Level::Info, "{}", name);
+        assert_eq!(input.args[0].private, None);
+    }
+
+    #[test]
+    fn test_expansion_logs_through_capture() {
+        let capture = oslog::testing::CaptureBackend::new();
+        let log = capture.log("com.example.test", "Static");
+        let name = "alice";
+
+        oslog::os_log_static!(
+            log,
+            oslog::Level::Info,
+            "{} has {:d} items, {:x} {:f}%",
+            name,
+            -3i32,
+            public(255u8),
+            1.5f32
+        );
+        oslog::os_log_static!(&log, oslog::Level::Error, "{}", public(name));
+
+        capture.assert_logged(oslog::Level::Info, "<private> has -3 items, ff 1.500000%");
+        capture.assert_logged(oslog::Level::Error, "alice");
+    }
+
+    #[test]
+    fn test_expansion_doesnt_shadow_arguments() {
+        let capture = oslog::testing::CaptureBackend::new();
+        let log = capture.log("com.example.test", "Static");
+        let other = capture.log("com.example.test", "Other");
+        let level = 7u32;
+        let format = "name";
+
+        oslog::os_log_static!(
+            other,
+            oslog::Level::Info,
+            "{:u} {} {}",
+            public(level),
+            public(format),
+            public(log.level_is_enabled(oslog::Level::Info))
+        );
+
+        capture.assert_logged(oslog::Level::Info, "7 name true");
+        assert_eq!(capture.for_category("Other").len(), 1);
+    }
+
+    #[test]
+    fn test_expansion_sanitises_arguments() {
+        let capture = oslog::testing::CaptureBackend::new();
+        let log = capture.log("com.example.test", "Static");
+        let escaped = log.clone().with_nul_policy(oslog::NulPolicy::Escape);
+        let rejected = log.clone().with_nul_policy(oslog::NulPolicy::Reject);
+        let stripped = log
+            .clone()
+            .with_control_chars(oslog::ControlChars::Strip)
+            .with_hash_mask(Some(oslog::HashMask::with_salt(b"salt")));
+
+        oslog::os_log_static!(log, oslog::Level::Info, "{}", public("a\0b"));
+        oslog::os_log_static!(escaped, oslog::Level::Info, "{}", public("a\0b"));
+        oslog::os_log_static!(rejected, oslog::Level::Info, "{} {:d}", public("a\0b"), 1);
+        oslog::os_log_static!(rejected, oslog::Level::Info, "{:d}", 2);
+        oslog::os_log_static!(stripped, oslog::Level::Info, "{}", public("\x1b[31mred"));
+        // The hash mask doesn't apply.
+        oslog::os_log_static!(stripped, oslog::Level::Info, "{}", "secret");
+
+        let messages: Vec<_> = capture
+            .take()
+            .iter()
+            .map(|e| e.message_str().into_owned())
+            .collect();
+        assert_eq!(messages, ["a(null)b", "a\\0b", "2", "red", "<private>"]);
+    }
+}
@@ -0,0 +1,274 @@
+//! `#[derive(OsLogValue)]`, which implements `Sensitive` and `Debug` with
+//! marked fields wrapped so they stay out of the log.
+
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::ext::IdentExt;
+use syn::{Data, DeriveInput, Field, Fields, GenericParam, Ident};
+
+/// How a field is logged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Treatment {
+    Plain,
+    Private,
+    Hash,
+    Skip,
+}
+
+impl Treatment {
+    fn parse(field: &Field) -> syn::Result<Self> {
+        let mut treatment = Self::Plain;
+
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("oslog")) {
+            attr.parse_nested_meta(|meta| {
+                let parsed = if meta.path.is_ident("private") {
+                    Self::Private
+                } else if meta.path.is_ident("hash") {
+                    Self::Hash
+                } else if meta.path.is_ident("skip") {
+                    Self::Skip
+                } else {
+                    return Err(meta.error(
+                        "unsupported oslog attribute, expected `private`, `hash` or `skip`",
+                    ));
+                };
+
+                if treatment != Self::Plain {
+                    return Err(meta.error("fields can only have one oslog attribute"));
+                }
+                treatment = parsed;
+                Ok(())
+            })?;
+        }
+
+        Ok(treatment)
+    }
+}
+
+pub(crate) fn expand(mut input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+
+    let arms = match &input.data {
+        Data::Struct(data) => vec![arm(quote!(Self), name, &data.fields)?],
+        Data::Enum(data) => data
+            .variants
+            .iter()
+            .map(|variant| {
+                let ident = &variant.ident;
+                arm(quote!(Self::#ident), ident, &variant.fields)
+            })
+            .collect::<syn::Result<_>>()?,
+        Data::Union(data) => {
+            return Err(syn::Error::new(
+                data.union_token.span,
+                "OsLogValue can't be derived for unions",
+            ))
+        }
+    };
+
+    // Like `#[derive(Debug)]`, type parameters have to be `Debug`.
+    let bounded: Vec<Ident> = input
+        .generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            GenericParam::Type(param) => Some(param.ident.clone()),
+            _ => None,
+        })
+        .collect();
+    let where_clause = input.generics.make_where_clause();
+    for ident in bounded {
+        where_clause
+            .predicates
+            .push(syn::parse_quote!(#ident: ::core::fmt::Debug));
+    }
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::oslog::Sensitive for #name #ty_generics #where_clause {
+            fn fmt_sensitive(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                match *self {
+                    #(#arms)*
+                }
+            }
+        }
+
+        impl #impl_generics ::core::fmt::Debug for #name #ty_generics #where_clause {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                ::oslog::Sensitive::fmt_sensitive(self, f)
+            }
+        }
+    })
+}
+
+/// A match arm formatting one struct or variant, shaped like `Debug`'s output.
+fn arm(path: TokenStream2, name: &Ident, fields: &Fields) -> syn::Result<TokenStream2> {
+    let name = name.unraw().to_string();
+
+    let mut patterns = Vec::new();
+    let mut values = Vec::new();
+    let mut skipped = false;
+    for (i, field) in fields.iter().enumerate() {
+        let treatment = Treatment::parse(field)?;
+        let binding = format_ident!("__oslog_field{}", i, span = Span::mixed_site());
+
+        let pattern = if treatment == Treatment::Skip {
+            skipped = true;
+            quote!(_)
+        } else {
+            quote!(ref #binding)
+        };
+        patterns.push(match &field.ident {
+            Some(ident) => quote!(#ident: #pattern),
+            None => pattern,
+        });
+
+        let value = match treatment {
+            Treatment::Plain => quote!(#binding),
+            Treatment::Private => quote!(&::oslog::Private(#binding)),
+            Treatment::Hash => quote!(&::oslog::Hashed(#binding)),
+            Treatment::Skip => continue,
+        };
+        values.push(match &field.ident {
+            Some(ident) => {
+                let field_name = ident.unraw().to_string();
+                quote!(.field(#field_name, #value))
+            }
+            None => quote!(.field(#value)),
+        });
+    }
+
+    Ok(match fields {
+        Fields::Named(_) => {
+            let finish = if skipped {
+                quote!(finish_non_exhaustive)
+            } else {
+                quote!(finish)
+            };
+            quote! {
+                #path { #(#patterns),* } => f.debug_struct(#name) #(#values)* .#finish(),
+            }
+        }
+        Fields::Unnamed(_) => {
+            let finish = if skipped {
+                quote!(finish_non_exhaustive)
+            } else {
+                quote!(finish)
+            };
+            quote! {
+                #path ( #(#patterns),* ) => f.debug_tuple(#name) #(#values)* .#finish(),
+            }
+        }
+        Fields::Unit => quote! {
+            #path => f.write_str(#name),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oslog::testing::CaptureBackend;
+    use oslog::{HashMask, Level, OsLogValue};
+
+    #[derive(OsLogValue)]
+    struct User {
+        name: String,
+        #[oslog(private)]
+        email: String,
+        #[oslog(hash)]
+        id: u64,
+        #[oslog(skip)]
+        #[allow(dead_code)]
+        password: String,
+    }
+
+    #[derive(OsLogValue)]
+    enum Event<T> {
+        SignedIn(#[oslog(private)] T, u8),
+        SignedOut { r#type: T },
+        Expired,
+    }
+
+    #[derive(OsLogValue)]
+    struct Session(
+        u8,
+        #[oslog(skip)]
+        #[allow(dead_code)]
+        String,
+    );
+
+    fn user() -> User {
+        User {
+            name: "alice".into(),
+            email: "alice@example.com".into(),
+            id: 42,
+            password: "hunter2".into(),
+        }
+    }
+
+    #[test]
+    fn test_derive_logs_through_capture() {
+        let capture = CaptureBackend::new();
+        let mask = HashMask::with_salt(b"salt");
+        let log = capture.log("com.example.test", "Users");
+        let masked = log.clone().with_hash_mask(Some(mask));
+
+        log.log_fmt(Level::Info, format_args!("{:?}", user()));
+        masked.log_fmt(Level::Info, format_args!("{:?}", user()));
+        log.log_fmt(Level::Info, format_args!("{:?}", Event::SignedIn("bob", 2)));
+
+        let id = mask.mask(b"42");
+        capture.assert_logged(
+            Level::Info,
+            &format!(
+                "User {{ name: \"alice\", email: <private>, id: {}, .. }}",
+                HashMask::per_process().mask(b"42")
+            ),
+        );
+        capture.assert_logged(
+            Level::Info,
+            &format!(
+                "User {{ name: \"alice\", email: {}, id: {}, .. }}",
+                mask.mask(b"\"alice@example.com\""),
+                id
+            ),
+        );
+        capture.assert_logged(Level::Info, "SignedIn(<private>, 2)");
+    }
+
+    #[test]
+    fn test_derive_outside_logging() {
+        assert_eq!(
+            format!("{:?}", Event::SignedOut { r#type: 1 }),
+            "SignedOut { type: 1 }"
+        );
+        assert_eq!(format!("{:?}", Event::<u8>::Expired), "Expired");
+        assert_eq!(format!("{:?}", Session(1, "key".into())), "Session(1, ..)");
+        assert!(!format!("{:?}", user()).contains("alice@"));
+    }
+
+    #[test]
+    fn test_attribute_errors() {
+        let input: DeriveInput = syn::parse_quote! {
+            struct A {
+                #[oslog(private, hash)]
+                a: u8,
+            }
+        };
+        let err = expand(input).unwrap_err();
+        assert!(err.to_string().contains("only have one oslog attribute"));
+
+        let input: DeriveInput = syn::parse_quote! {
+            struct A(#[oslog(public)] u8);
+        };
+        let err = expand(input).unwrap_err();
+        assert!(err.to_string().contains("unsupported oslog attribute"));
+
+        let input: DeriveInput = syn::parse_quote! {
+            union A { a: u8 }
+        };
+        let err = expand(input).unwrap_err();
+        assert!(err.to_string().contains("unions"));
+    }
+}
@@ -0,0 +1,213 @@
+use super::{Backend, Handle};
+use crate::Error;
+use std::ffi::CStr;
+
+#[cfg(target_vendor = "apple")]
+use super::{redact, Argument, Part, StaticFormat};
+#[cfg(target_vendor = "apple")]
+use crate::encoder::{Encoder, MAX_ARGUMENTS};
+#[cfg(target_vendor = "apple")]
+use crate::sys::*;
+#[cfg(target_vendor = "apple")]
+use crate::{Level, Privacy};
+#[cfg(target_vendor = "apple")]
+use std::ffi::c_void;
+
+/// Forwards to Apple's unified logging system. On other targets creating a
+/// log fails with [`Error::UnsupportedPlatform`].
+pub struct AppleBackend;
+
+#[cfg(target_vendor = "apple")]
+impl Backend for AppleBackend {
+    fn create(&self, subsystem: &CStr, category: &CStr) -> Result<Box<dyn Handle>, Error> {
+        let inner = unsafe { os_log_create(subsystem.as_ptr(), category.as_ptr()) };
+        AppleHandle::boxed(inner)
+    }
+
+    fn global(&self) -> Result<Box<dyn Handle>, Error> {
+        let inner = default_log();
+        AppleHandle::boxed(inner)
+    }
+
+    fn disabled(&self) -> Box<dyn Handle> {
+        Box::new(AppleHandle {
+            inner: disabled_log(),
+        })
+    }
+}
+
+#[cfg(not(target_vendor = "apple"))]
+impl Backend for AppleBackend {
+    fn create(&self, _subsystem: &CStr, _category: &CStr) -> Result<Box<dyn Handle>, Error> {
+        Err(Error::UnsupportedPlatform)
+    }
+
+    fn global(&self) -> Result<Box<dyn Handle>, Error> {
+        Err(Error::UnsupportedPlatform)
+    }
+}
+
+// The log stores format strings as offsets into the binary, so they must be
+// constants. These are placed where clang puts them.
+#[cfg(target_vendor = "apple")]
+include!(concat!(env!("OUT_DIR"), "/formats.rs"));
+
+#[cfg(target_vendor = "apple")]
+struct AppleHandle {
+    inner: os_log_t,
+}
+
+#[cfg(target_vendor = "apple")]
+unsafe impl Send for AppleHandle {}
+#[cfg(target_vendor = "apple")]
+unsafe impl Sync for AppleHandle {}
+
+#[cfg(target_vendor = "apple")]
+impl AppleHandle {
+    fn boxed(inner: os_log_t) -> Result<Box<dyn Handle>, Error> {
+        if inner.is_null() {
+            Err(Error::NullHandle)
+        } else {
+            Ok(Box::new(Self { inner }))
+        }
+    }
+
+    /// The default and disabled logs are static and mustn't be retained or
+    /// released.
+    fn is_static(&self) -> bool {
+        self.inner == default_log() || self.inner == disabled_log()
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+impl AppleHandle {
+    /// `format` must be a constant in the binary, e.g. one of the generated
+    /// formats.
+    fn log_encoded(&self, level: Level, format: &'static [u8], encoder: &Encoder<'_>) {
+        let buffer = encoder.as_bytes();
+
+        unsafe {
+            _os_log_impl(
+                std::ptr::addr_of!(__dso_handle) as *mut c_void,
+                self.inner,
+                level as u8,
+                format.as_ptr() as *const _,
+                buffer.as_ptr(),
+                buffer.len() as u32,
+            );
+        }
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+impl Drop for AppleHandle {
+    fn drop(&mut self) {
+        if !self.is_static() {
+            unsafe { os_release(self.inner as *mut c_void) };
+        }
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+impl Handle for AppleHandle {
+    fn clone_handle(&self) -> Box<dyn Handle> {
+        if !self.is_static() {
+            unsafe { os_retain(self.inner as *mut c_void) };
+        }
+        Box::new(Self { inner: self.inner })
+    }
+
+    #[cfg(not(feature = "rust-encoder"))]
+    #[inline]
+    fn log(&self, level: Level, message: &CStr) {
+        unsafe {
+            match level {
+                Level::Debug => wrapped_os_log_debug(self.inner, message.as_ptr()),
+                Level::Info => wrapped_os_log_info(self.inner, message.as_ptr()),
+                Level::Default => wrapped_os_log_default(self.inner, message.as_ptr()),
+                Level::Error => wrapped_os_log_error(self.inner, message.as_ptr()),
+                Level::Fault => wrapped_os_log_fault(self.inner, message.as_ptr()),
+            }
+        }
+    }
+
+    #[cfg(feature = "rust-encoder")]
+    #[inline]
+    fn log(&self, level: Level, message: &CStr) {
+        // Like the os_log_* macros, skip encoding when the level is disabled.
+        if !self.level_is_enabled(level) {
+            return;
+        }
+
+        let mut encoder = Encoder::new();
+        encoder.push_str(message, Privacy::Public);
+        self.log_encoded(level, &FORMAT_0_1, &encoder);
+    }
+
+    fn log_parts(&self, level: Level, parts: &[Part<'_>]) {
+        if !self.level_is_enabled(level) {
+            return;
+        }
+
+        // There's only a format for alternating public and private parts.
+        let alternating = parts.windows(2).all(|w| w[0].privacy != w[1].privacy);
+        let Some(first) = parts.first() else { return };
+        if !alternating || parts.len() > MAX_ARGUMENTS {
+            return self.log(level, &redact(parts));
+        }
+
+        let mut encoder = Encoder::new();
+        for part in parts {
+            encoder.push_str(part.text, part.privacy);
+        }
+
+        let format = FORMATS[(first.privacy == Privacy::Private) as usize][parts.len() - 1];
+        self.log_encoded(level, format, &encoder);
+    }
+
+    fn log_static(&self, level: Level, format: &StaticFormat, args: &[(Privacy, Argument<'_>)]) {
+        if !self.level_is_enabled(level) {
+            return;
+        }
+
+        if args.len() > MAX_ARGUMENTS {
+            return self.log(level, &format.render(args));
+        }
+
+        let mut encoder = Encoder::new();
+        for (privacy, arg) in args {
+            match *arg {
+                Argument::Signed(value) => encoder.push_i64(value, *privacy),
+                Argument::Unsigned(value) | Argument::Hex(value) => {
+                    encoder.push_u64(value, *privacy)
+                }
+                Argument::Float(value) => encoder.push_f64(value, *privacy),
+                Argument::Str(value) => encoder.push_str(value, *privacy),
+            };
+        }
+
+        self.log_encoded(level, format.format.to_bytes_with_nul(), &encoder);
+    }
+
+    #[inline]
+    fn level_is_enabled(&self, level: Level) -> bool {
+        unsafe { os_log_type_enabled(self.inner, level as u8) }
+    }
+}
+
+#[cfg(all(test, not(target_vendor = "apple")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unsupported_platform() {
+        assert_eq!(
+            AppleBackend.create(c"com.example.test", c"category").err(),
+            Some(Error::UnsupportedPlatform)
+        );
+        assert_eq!(
+            AppleBackend.global().err(),
+            Some(Error::UnsupportedPlatform)
+        );
+    }
+}
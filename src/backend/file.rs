@@ -0,0 +1,394 @@
+use super::timestamp::rfc3339;
+use super::{Backend, Handle};
+use crate::chunk::escape_newlines;
+use crate::Error;
+use crate::Level;
+use std::ffi::CStr;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// How [`FileBackend`] writes each entry.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FileFormat {
+    /// `<timestamp> <pid>:<tid> <level> [<subsystem>:<category>] <message>`,
+    /// with newlines in the message escaped as `\n` and `\r`.
+    #[default]
+    Text,
+    /// One JSON object per line with `timestamp`, `pid`, `tid`, `subsystem`,
+    /// `category`, `level` and `message` keys.
+    Json,
+}
+
+/// Appends entries to a file, rotating it by size and age.
+///
+/// When the file is rotated it's renamed to `<path>.1`, any existing
+/// `<path>.1` to `<path>.2` and so on, keeping at most `max_files` old files.
+/// The file is opened on the first write, and write failures are ignored as
+/// there's nowhere to report them. If the file can't be rotated, entries are
+/// appended to it regardless and rotation is retried a minute later.
+#[derive(Clone, Debug)]
+pub struct FileBackend {
+    path: PathBuf,
+    format: FileFormat,
+    max_size: Option<u64>,
+    max_age: Option<Duration>,
+    max_files: usize,
+    state: Arc<Mutex<Option<OpenFile>>>,
+}
+
+#[derive(Debug)]
+struct OpenFile {
+    file: File,
+    size: u64,
+    created: SystemTime,
+    /// Set after rotation fails, so it isn't attempted on every write.
+    retry_rotation: Option<SystemTime>,
+}
+
+const ROTATION_RETRY: Duration = Duration::from_secs(60);
+
+impl FileBackend {
+    /// By default the file is rotated once it reaches 10 MiB, and 5 old files
+    /// are kept.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_owned(),
+            format: FileFormat::default(),
+            max_size: Some(10 * 1024 * 1024),
+            max_age: None,
+            max_files: 5,
+            state: Arc::default(),
+        }
+    }
+
+    pub fn with_format(mut self, format: FileFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Rotates the file before it would grow beyond `max_size` bytes, or
+    /// never if `None`.
+    pub fn with_max_size(mut self, max_size: Option<u64>) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Rotates the file once it's older than `max_age`, or never if `None`.
+    ///
+    /// The age is measured from the file's creation time. On filesystems
+    /// which don't record it, it's measured from when the file was first
+    /// opened by this process instead, so restarting the process resets it.
+    pub fn with_max_age(mut self, max_age: Option<Duration>) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Sets how many rotated files are kept in addition to the current one.
+    pub fn with_max_files(mut self, max_files: usize) -> Self {
+        self.max_files = max_files;
+        self
+    }
+
+    fn write(&self, entry: &[u8], now: SystemTime) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Some(open) = state.as_mut() {
+            let too_big = self
+                .max_size
+                .is_some_and(|max| open.size > 0 && open.size + entry.len() as u64 > max);
+            let too_old = self.max_age.is_some_and(|max| open.created + max <= now);
+            let retry = open.retry_rotation.is_none_or(|at| at <= now);
+
+            if (too_big || too_old) && retry {
+                match self.rotate() {
+                    Ok(()) => *state = None,
+                    Err(_) => open.retry_rotation = Some(now + ROTATION_RETRY),
+                }
+            }
+        }
+
+        let open = match state.as_mut() {
+            Some(open) => open,
+            None => state.insert(self.open(now)?),
+        };
+
+        open.file.write_all(entry)?;
+        open.size += entry.len() as u64;
+        Ok(())
+    }
+
+    fn open(&self, now: SystemTime) -> io::Result<OpenFile> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        let metadata = file.metadata()?;
+
+        Ok(OpenFile {
+            file,
+            size: metadata.len(),
+            created: metadata.created().unwrap_or(now),
+            retry_rotation: None,
+        })
+    }
+
+    fn rotate(&self) -> io::Result<()> {
+        let rotated = |n: usize| {
+            let mut path = self.path.clone().into_os_string();
+            path.push(format!(".{}", n));
+            PathBuf::from(path)
+        };
+
+        if self.max_files == 0 {
+            return fs::remove_file(&self.path);
+        }
+
+        let _ = fs::remove_file(rotated(self.max_files));
+        for n in (1..self.max_files).rev() {
+            let _ = fs::rename(rotated(n), rotated(n + 1));
+        }
+        fs::rename(&self.path, rotated(1))
+    }
+}
+
+impl Backend for FileBackend {
+    fn create(&self, subsystem: &CStr, category: &CStr) -> Result<Box<dyn Handle>, Error> {
+        Ok(Box::new(FileHandle {
+            subsystem: subsystem.to_string_lossy().into_owned(),
+            category: category.to_string_lossy().into_owned(),
+            backend: self.clone(),
+        }))
+    }
+
+    fn global(&self) -> Result<Box<dyn Handle>, Error> {
+        self.create(c"", c"")
+    }
+}
+
+#[derive(Clone)]
+struct FileHandle {
+    subsystem: String,
+    category: String,
+    backend: FileBackend,
+}
+
+impl FileHandle {
+    fn format(&self, level: Level, message: &str, time: SystemTime, tid: u64) -> String {
+        let timestamp = rfc3339(time);
+        let pid = std::process::id();
+
+        match self.backend.format {
+            FileFormat::Text => format!(
+                "{} {}:{} {} [{}:{}] {}\n",
+                timestamp,
+                pid,
+                tid,
+                level.as_str(),
+                self.subsystem,
+                self.category,
+                escape_newlines(message)
+            ),
+            FileFormat::Json => format!(
+                "{{\"timestamp\":\"{}\",\"pid\":{},\"tid\":{},\"subsystem\":{},\"category\":{},\"level\":\"{}\",\"message\":{}}}\n",
+                timestamp,
+                pid,
+                tid,
+                json_string(&self.subsystem),
+                json_string(&self.category),
+                level.as_str(),
+                json_string(message)
+            ),
+        }
+    }
+}
+
+impl Handle for FileHandle {
+    fn clone_handle(&self) -> Box<dyn Handle> {
+        Box::new(self.clone())
+    }
+
+    fn log(&self, level: Level, message: &CStr) {
+        let now = SystemTime::now();
+        let entry = self.format(level, &message.to_string_lossy(), now, crate::thread::id());
+        let _ = self.backend.write(entry.as_bytes(), now);
+    }
+
+    fn level_is_enabled(&self, _level: Level) -> bool {
+        true
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("oslog-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn handle(backend: &FileBackend) -> FileHandle {
+        FileHandle {
+            subsystem: String::from("com.example.test"),
+            category: String::from("Settings"),
+            backend: backend.clone(),
+        }
+    }
+
+    #[test]
+    fn test_text_format() {
+        let backend = FileBackend::new("unused");
+        assert_eq!(
+            handle(&backend).format(Level::Error, "Hello", UNIX_EPOCH, 7),
+            format!(
+                "1970-01-01T00:00:00.000000Z {}:7 Error [com.example.test:Settings] Hello\n",
+                std::process::id()
+            )
+        );
+    }
+
+    #[test]
+    fn test_text_format_escapes_newlines() {
+        let backend = FileBackend::new("unused");
+        let entry = handle(&backend).format(Level::Info, "a\nforged\r\n", UNIX_EPOCH, 7);
+        assert!(entry.ends_with("] a\\nforged\\r\\n\n"));
+        assert_eq!(entry.lines().count(), 1);
+    }
+
+    #[test]
+    fn test_json_format() {
+        let backend = FileBackend::new("unused").with_format(FileFormat::Json);
+        assert_eq!(
+            handle(&backend).format(Level::Info, "\"a\"\n\u{1}\u{1F601}", UNIX_EPOCH, 7),
+            format!(
+                "{{\"timestamp\":\"1970-01-01T00:00:00.000000Z\",\"pid\":{},\"tid\":7,\"subsystem\":\"com.example.test\",\"category\":\"Settings\",\"level\":\"Info\",\"message\":\"\\\"a\\\"\\n\\u0001\u{1F601}\"}}\n",
+                std::process::id()
+            )
+        );
+    }
+
+    #[test]
+    fn test_writes_through_os_log() {
+        let dir = temp_dir("file-write");
+        let path = dir.join("app.log");
+        let log =
+            crate::OsLog::with_backend(&FileBackend::new(&path), "com.example.test", "Settings");
+        log.info("One");
+        log.fault("Two");
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let lines: Vec<_> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("Info [com.example.test:Settings] One"));
+        assert!(lines[1].ends_with("Fault [com.example.test:Settings] Two"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rotates_by_size() {
+        let dir = temp_dir("file-size");
+        let path = dir.join("app.log");
+        let backend = FileBackend::new(&path)
+            .with_max_size(Some(10))
+            .with_max_files(2);
+
+        for entry in ["one\n", "two\n", "three\n", "four\n", "five\n"] {
+            backend.write(entry.as_bytes(), SystemTime::now()).unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "four\nfive\n");
+        assert_eq!(
+            fs::read_to_string(dir.join("app.log.1")).unwrap(),
+            "three\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("app.log.2")).unwrap(),
+            "one\ntwo\n"
+        );
+        assert!(!dir.join("app.log.3").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_keeps_writing_when_rotation_fails() {
+        let dir = temp_dir("file-rotate-fails");
+        let path = dir.join("app.log");
+        let backend = FileBackend::new(&path)
+            .with_max_size(Some(4))
+            .with_max_files(1);
+
+        // A non-empty directory in the way stops the file being renamed.
+        let blocker = dir.join("app.log.1");
+        fs::create_dir_all(blocker.join("blocker")).unwrap();
+
+        let now = SystemTime::now();
+        for entry in ["one\n", "two\n", "three\n"] {
+            backend.write(entry.as_bytes(), now).unwrap();
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one\ntwo\nthree\n");
+
+        fs::remove_dir_all(&blocker).unwrap();
+        backend.write(b"four\n", now).unwrap();
+        assert!(!blocker.exists());
+
+        backend.write(b"five\n", now + ROTATION_RETRY).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "five\n");
+        assert_eq!(
+            fs::read_to_string(&blocker).unwrap(),
+            "one\ntwo\nthree\nfour\n"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rotates_by_age() {
+        let dir = temp_dir("file-age");
+        let path = dir.join("app.log");
+        let backend = FileBackend::new(&path)
+            .with_max_size(None)
+            .with_max_age(Some(Duration::from_secs(60)))
+            .with_max_files(1);
+
+        let now = SystemTime::now();
+        backend.write(b"old\n", now).unwrap();
+        backend.write(b"still old\n", now).unwrap();
+        backend
+            .write(b"new\n", now + Duration::from_secs(61))
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new\n");
+        assert_eq!(
+            fs::read_to_string(dir.join("app.log.1")).unwrap(),
+            "old\nstill old\n"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
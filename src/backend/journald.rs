@@ -0,0 +1,186 @@
+use super::syslog::severity;
+use super::{Backend, Handle};
+use crate::Error;
+use crate::Level;
+use std::ffi::CStr;
+use std::io;
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Sends messages to systemd-journald using its native protocol, keeping the
+/// subsystem and category as `OSLOG_SUBSYSTEM` and `OSLOG_CATEGORY` fields.
+///
+/// `SYSLOG_IDENTIFIER` is the subsystem, or the executable's name for the
+/// global log. Messages too large for a single datagram are dropped, as
+/// passing them through a memfd isn't supported.
+#[derive(Clone, Debug)]
+pub struct JournaldBackend {
+    socket: Arc<UnixDatagram>,
+    path: PathBuf,
+}
+
+impl JournaldBackend {
+    pub const DEFAULT_PATH: &'static str = "/run/systemd/journal/socket";
+
+    pub fn new() -> io::Result<Self> {
+        Ok(Self {
+            socket: Arc::new(UnixDatagram::unbound()?),
+            path: PathBuf::from(Self::DEFAULT_PATH),
+        })
+    }
+
+    /// Sets the socket messages are sent to. Defaults to
+    /// `/run/systemd/journal/socket`.
+    pub fn with_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.path = path.as_ref().to_owned();
+        self
+    }
+}
+
+impl Backend for JournaldBackend {
+    fn create(&self, subsystem: &CStr, category: &CStr) -> Result<Box<dyn Handle>, Error> {
+        let identifier = if subsystem.is_empty() {
+            executable_name()
+        } else {
+            subsystem.to_bytes().to_vec()
+        };
+
+        // Everything except the message and priority is fixed for the
+        // lifetime of the log.
+        let mut fields = Vec::new();
+        append_field(&mut fields, "SYSLOG_IDENTIFIER", &identifier);
+        append_field(&mut fields, "OSLOG_SUBSYSTEM", subsystem.to_bytes());
+        append_field(&mut fields, "OSLOG_CATEGORY", category.to_bytes());
+
+        Ok(Box::new(JournaldHandle {
+            backend: self.clone(),
+            fields,
+        }))
+    }
+
+    fn global(&self) -> Result<Box<dyn Handle>, Error> {
+        self.create(c"", c"")
+    }
+}
+
+#[derive(Clone)]
+struct JournaldHandle {
+    backend: JournaldBackend,
+    fields: Vec<u8>,
+}
+
+impl JournaldHandle {
+    fn format(&self, level: Level, message: &CStr) -> Vec<u8> {
+        let mut packet = Vec::with_capacity(self.fields.len() + message.count_bytes() + 32);
+        append_field(&mut packet, "MESSAGE", message.to_bytes());
+        append_field(
+            &mut packet,
+            "PRIORITY",
+            severity(level).to_string().as_bytes(),
+        );
+        packet.extend_from_slice(&self.fields);
+        packet
+    }
+}
+
+impl Handle for JournaldHandle {
+    fn clone_handle(&self) -> Box<dyn Handle> {
+        Box::new(self.clone())
+    }
+
+    fn log(&self, level: Level, message: &CStr) {
+        let packet = self.format(level, message);
+        // There's nowhere to report failures, e.g. when journald isn't running.
+        let _ = self.backend.socket.send_to(&packet, &self.backend.path);
+    }
+
+    fn level_is_enabled(&self, _level: Level) -> bool {
+        true
+    }
+}
+
+/// Values containing a newline use the binary form: the name, a newline, the
+/// value's length as a little endian u64, then the value.
+fn append_field(packet: &mut Vec<u8>, name: &str, value: &[u8]) {
+    packet.extend_from_slice(name.as_bytes());
+    if value.contains(&b'\n') {
+        packet.push(b'\n');
+        packet.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        packet.push(b'=');
+    }
+    packet.extend_from_slice(value);
+    packet.push(b'\n');
+}
+
+fn executable_name() -> Vec<u8> {
+    std::env::current_exe()
+        .ok()
+        .and_then(|path| Some(path.file_name()?.to_string_lossy().into_owned()))
+        .unwrap_or_default()
+        .into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_line_field() {
+        let mut packet = Vec::new();
+        append_field(&mut packet, "MESSAGE", b"Hello");
+        assert_eq!(packet, b"MESSAGE=Hello\n");
+    }
+
+    #[test]
+    fn test_multi_line_field() {
+        let mut packet = Vec::new();
+        append_field(&mut packet, "MESSAGE", b"a\nb");
+        assert_eq!(packet, b"MESSAGE\n\x03\0\0\0\0\0\0\0a\nb\n");
+    }
+
+    #[test]
+    fn test_send() {
+        let path = std::env::temp_dir().join(format!("oslog-journald-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let server = UnixDatagram::bind(&path).unwrap();
+
+        let backend = JournaldBackend::new().unwrap().with_path(&path);
+        let log = crate::OsLog::with_backend(&backend, "com.example.test", "Settings");
+        log.fault("Line one\nLine two");
+
+        let mut buf = [0; 1024];
+        let len = server.recv(&mut buf).unwrap();
+        assert_eq!(
+            &buf[..len],
+            b"MESSAGE\n\x11\0\0\0\0\0\0\0Line one\nLine two\n\
+              PRIORITY=2\n\
+              SYSLOG_IDENTIFIER=com.example.test\n\
+              OSLOG_SUBSYSTEM=com.example.test\n\
+              OSLOG_CATEGORY=Settings\n"
+        );
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_global_identifier() {
+        let path =
+            std::env::temp_dir().join(format!("oslog-journald-global-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let server = UnixDatagram::bind(&path).unwrap();
+
+        let backend = JournaldBackend::new().unwrap().with_path(&path);
+        backend.global().unwrap().log(Level::Info, c"Hi");
+
+        let mut buf = [0; 1024];
+        let len = server.recv(&mut buf).unwrap();
+        let mut expected = b"MESSAGE=Hi\nPRIORITY=6\nSYSLOG_IDENTIFIER=".to_vec();
+        expected.extend_from_slice(&executable_name());
+        expected.extend_from_slice(b"\nOSLOG_SUBSYSTEM=\nOSLOG_CATEGORY=\n");
+        assert_eq!(&buf[..len], expected);
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,181 @@
+//! The layer between [`OsLog`](crate::OsLog) and whatever ends up recording
+//! the messages.
+//!
+//! On Apple targets the [`platform`] backend forwards to the unified logging
+//! system. Everywhere else it falls back to [`StderrBackend`] so code using
+//! `OsLog` and `OsLogger` compiles and runs unchanged.
+
+mod apple;
+mod file;
+#[cfg(unix)]
+mod journald;
+mod stderr;
+#[cfg(unix)]
+mod syslog;
+mod timestamp;
+
+pub use apple::AppleBackend;
+pub use file::{FileBackend, FileFormat};
+#[cfg(unix)]
+pub use journald::JournaldBackend;
+pub use stderr::{ColorChoice, StderrBackend};
+#[cfg(unix)]
+pub use syslog::{Facility, SyslogBackend};
+
+use crate::{Error, Level, NulPolicy, Privacy};
+use std::ffi::{CStr, CString};
+
+/// Creates the per-log [`Handle`]s that messages are written to.
+pub trait Backend: Send + Sync {
+    /// Creates a handle for `subsystem` and `category`.
+    fn create(&self, subsystem: &CStr, category: &CStr) -> Result<Box<dyn Handle>, Error>;
+
+    /// Returns the handle used by [`OsLog::global`](crate::OsLog::global).
+    fn global(&self) -> Result<Box<dyn Handle>, Error>;
+
+    /// Returns a handle which discards everything, as used by
+    /// [`OsLog::disabled`](crate::OsLog::disabled).
+    fn disabled(&self) -> Box<dyn Handle> {
+        Box::new(DisabledHandle)
+    }
+}
+
+/// A single log created by a [`Backend`].
+pub trait Handle: Send + Sync {
+    /// Returns a handle to the same log, as used by `OsLog`'s `Clone`.
+    fn clone_handle(&self) -> Box<dyn Handle>;
+
+    /// Writes `message` at `level`. The message has already been sanitised.
+    fn log(&self, level: Level, message: &CStr);
+
+    /// Writes a message made of `parts` at `level`, as used by
+    /// [`os_log!`](crate::os_log). By default private parts are replaced with
+    /// `<private>` and the result is passed to `log`.
+    fn log_parts(&self, level: Level, parts: &[Part<'_>]) {
+        self.log(level, &redact(parts));
+    }
+
+    /// Writes a message with a format string known at compile time, as used
+    /// by `os_log_static!`. By default the message is rendered from the
+    /// format's pieces, with private arguments replaced by `<private>`, and
+    /// passed to `log`.
+    fn log_static(&self, level: Level, format: &StaticFormat, args: &[(Privacy, Argument<'_>)]) {
+        self.log(level, &format.render(args));
+    }
+
+    fn level_is_enabled(&self, level: Level) -> bool;
+}
+
+/// A piece of a message logged with [`Handle::log_parts`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Part<'a> {
+    pub privacy: Privacy,
+    pub text: &'a CStr,
+}
+
+/// Joins `parts`, replacing private ones with `<private>` like the unified log
+/// does when private data isn't enabled.
+pub fn redact(parts: &[Part<'_>]) -> CString {
+    let mut message = Vec::new();
+    for part in parts {
+        match part.privacy {
+            Privacy::Public => message.extend_from_slice(part.text.to_bytes()),
+            Privacy::Private => message.extend_from_slice(b"<private>"),
+        }
+    }
+    CString::new(message).expect("parts can't contain nul bytes")
+}
+
+/// A format string generated by `os_log_static!`.
+#[derive(Clone, Copy, Debug)]
+pub struct StaticFormat {
+    /// The printf style format passed to the unified log. It's a constant in
+    /// the calling binary.
+    pub format: &'static CStr,
+    /// The literal text around each argument, one more than the number of
+    /// arguments.
+    pub pieces: &'static [&'static str],
+}
+
+impl StaticFormat {
+    /// Interleaves the pieces with `args`, replacing private arguments with
+    /// `<private>`. `os_log_static!` rejects nul bytes in formats, but any in
+    /// hand-written pieces end the message, as they would end `format`.
+    pub fn render(&self, args: &[(Privacy, Argument<'_>)]) -> CString {
+        let mut message = String::new();
+        for (i, piece) in self.pieces.iter().enumerate() {
+            message.push_str(piece);
+            match args.get(i) {
+                Some((Privacy::Private, _)) => message.push_str("<private>"),
+                Some((Privacy::Public, arg)) => arg.render(&mut message),
+                None => {}
+            }
+        }
+        NulPolicy::Truncate.apply(&message).unwrap()
+    }
+}
+
+/// A typed argument of a [`StaticFormat`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Argument<'a> {
+    /// `%lld`
+    Signed(i64),
+    /// `%llu`
+    Unsigned(u64),
+    /// `%llx`
+    Hex(u64),
+    /// `%f`
+    Float(f64),
+    /// `%s`
+    Str(&'a CStr),
+}
+
+impl Argument<'_> {
+    /// Renders like the matching printf conversion.
+    fn render(&self, message: &mut String) {
+        use std::fmt::Write;
+
+        let _ = match self {
+            Self::Signed(value) => write!(message, "{}", value),
+            Self::Unsigned(value) => write!(message, "{}", value),
+            Self::Hex(value) => write!(message, "{:x}", value),
+            Self::Float(value) => write!(message, "{:.6}", value),
+            Self::Str(value) => write!(message, "{}", value.to_string_lossy()),
+        };
+    }
+}
+
+#[derive(Clone)]
+struct DisabledHandle;
+
+impl Handle for DisabledHandle {
+    fn clone_handle(&self) -> Box<dyn Handle> {
+        Box::new(self.clone())
+    }
+
+    fn log(&self, _level: Level, _message: &CStr) {}
+
+    fn log_parts(&self, _level: Level, _parts: &[Part<'_>]) {}
+
+    fn log_static(&self, _level: Level, _format: &StaticFormat, _args: &[(Privacy, Argument<'_>)]) {
+    }
+
+    fn level_is_enabled(&self, _level: Level) -> bool {
+        false
+    }
+}
+
+/// Returns the backend used by [`OsLog::new`](crate::OsLog::new) and
+/// [`OsLog::global`](crate::OsLog::global).
+#[cfg(target_vendor = "apple")]
+pub fn platform() -> &'static dyn Backend {
+    &AppleBackend
+}
+
+/// Returns the backend used by [`OsLog::new`](crate::OsLog::new) and
+/// [`OsLog::global`](crate::OsLog::global).
+#[cfg(not(target_vendor = "apple"))]
+pub fn platform() -> &'static dyn Backend {
+    static STDERR: std::sync::OnceLock<StderrBackend> = std::sync::OnceLock::new();
+    STDERR.get_or_init(StderrBackend::new)
+}
@@ -0,0 +1,211 @@
+use super::{Backend, Handle};
+use crate::placeholder::{self, Segment};
+use crate::Error;
+use crate::Level;
+use std::ffi::CStr;
+use std::io::{IsTerminal, Write};
+use std::sync::Arc;
+
+/// Whether [`StderrBackend`] colours the level of each line.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Colour only when stderr is a terminal.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Field {
+    Subsystem,
+    Category,
+    Level,
+    Message,
+}
+
+/// Writes one human readable line per message to stderr. This is the default
+/// backend on targets without the unified logging system.
+///
+/// The line format is a template where `{subsystem}`, `{category}`, `{level}`
+/// and `{message}` are substituted, and `{{` and `}}` produce literal braces.
+/// An empty subsystem or category, as used by the global log, renders as `-`.
+#[derive(Clone, Debug)]
+pub struct StderrBackend {
+    format: Arc<[Segment<Field>]>,
+    color: bool,
+}
+
+impl Default for StderrBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StderrBackend {
+    pub const DEFAULT_FORMAT: &'static str = "[{subsystem}:{category}] {level}: {message}";
+
+    pub fn new() -> Self {
+        Self {
+            format: parse_format(Self::DEFAULT_FORMAT).into(),
+            color: should_color(ColorChoice::Auto),
+        }
+    }
+
+    pub fn with_format(mut self, format: &str) -> Self {
+        self.format = parse_format(format).into();
+        self
+    }
+
+    pub fn with_color(mut self, choice: ColorChoice) -> Self {
+        self.color = should_color(choice);
+        self
+    }
+
+    fn format_line(&self, subsystem: &str, category: &str, level: Level, message: &str) -> String {
+        let or_nil = |s: &str| if s.is_empty() { "-" } else { s }.to_owned();
+
+        let mut line = String::with_capacity(message.len() + 32);
+        for segment in self.format.iter() {
+            match segment {
+                Segment::Text(text) => line.push_str(text),
+                Segment::Placeholder(Field::Subsystem) => line.push_str(&or_nil(subsystem)),
+                Segment::Placeholder(Field::Category) => line.push_str(&or_nil(category)),
+                Segment::Placeholder(Field::Level) if self.color => {
+                    line.push_str(&format!("\x1b[{}m{}\x1b[0m", color(level), level.as_str()))
+                }
+                Segment::Placeholder(Field::Level) => line.push_str(level.as_str()),
+                Segment::Placeholder(Field::Message) => line.push_str(message),
+            }
+        }
+        line.push('\n');
+        line
+    }
+}
+
+fn should_color(choice: ColorChoice) -> bool {
+    match choice {
+        ColorChoice::Auto => std::io::stderr().is_terminal(),
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    }
+}
+
+/// ANSI SGR parameters, loosely following Console's colouring.
+fn color(level: Level) -> &'static str {
+    match level {
+        Level::Debug => "90",
+        Level::Info => "36",
+        Level::Default => "39",
+        Level::Error => "33",
+        Level::Fault => "31",
+    }
+}
+
+fn parse_format(format: &str) -> Vec<Segment<Field>> {
+    placeholder::parse(
+        format,
+        &[
+            ("{subsystem}", Field::Subsystem),
+            ("{category}", Field::Category),
+            ("{level}", Field::Level),
+            ("{message}", Field::Message),
+        ],
+    )
+}
+
+impl Backend for StderrBackend {
+    fn create(&self, subsystem: &CStr, category: &CStr) -> Result<Box<dyn Handle>, Error> {
+        Ok(Box::new(StderrHandle {
+            subsystem: subsystem.to_string_lossy().into_owned(),
+            category: category.to_string_lossy().into_owned(),
+            backend: self.clone(),
+        }))
+    }
+
+    fn global(&self) -> Result<Box<dyn Handle>, Error> {
+        self.create(c"", c"")
+    }
+}
+
+#[derive(Clone)]
+struct StderrHandle {
+    subsystem: String,
+    category: String,
+    backend: StderrBackend,
+}
+
+impl Handle for StderrHandle {
+    fn clone_handle(&self) -> Box<dyn Handle> {
+        Box::new(self.clone())
+    }
+
+    fn log(&self, level: Level, message: &CStr) {
+        let line = self.backend.format_line(
+            &self.subsystem,
+            &self.category,
+            level,
+            &message.to_string_lossy(),
+        );
+        // Nowhere to report a failure to write to stderr.
+        let _ = std::io::stderr().lock().write_all(line.as_bytes());
+    }
+
+    fn level_is_enabled(&self, _level: Level) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_format() {
+        let backend = StderrBackend::new().with_color(ColorChoice::Never);
+        assert_eq!(
+            backend.format_line("com.example.test", "Settings", Level::Info, "Hello!"),
+            "[com.example.test:Settings] Info: Hello!\n"
+        );
+    }
+
+    #[test]
+    fn test_global_placeholders() {
+        let backend = StderrBackend::new().with_color(ColorChoice::Never);
+        assert_eq!(
+            backend.format_line("", "", Level::Fault, "Hello!"),
+            "[-:-] Fault: Hello!\n"
+        );
+    }
+
+    #[test]
+    fn test_custom_format() {
+        let backend = StderrBackend::new()
+            .with_format("{{{level}}} {category} \u{1F601} {message}")
+            .with_color(ColorChoice::Never);
+        assert_eq!(
+            backend.format_line("com.example.test", "Settings", Level::Debug, "Hi"),
+            "{Debug} Settings \u{1F601} Hi\n"
+        );
+    }
+
+    #[test]
+    fn test_color() {
+        let backend = StderrBackend::new()
+            .with_format("{level}")
+            .with_color(ColorChoice::Always);
+        assert_eq!(
+            backend.format_line("", "", Level::Error, ""),
+            "\x1b[33mError\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_write() {
+        let handle = StderrBackend::new()
+            .create(c"com.example.test", c"category")
+            .unwrap();
+        assert!(handle.level_is_enabled(Level::Debug));
+        handle.log(Level::Info, c"Hello!");
+    }
+}
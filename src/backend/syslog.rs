@@ -0,0 +1,273 @@
+use super::timestamp::rfc3339;
+use super::{Backend, Handle};
+use crate::Error;
+use crate::Level;
+use std::ffi::CStr;
+use std::io;
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// The syslog facility messages are logged under.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Facility {
+    #[default]
+    User = 1,
+    Daemon = 3,
+    Local0 = 16,
+    Local1 = 17,
+    Local2 = 18,
+    Local3 = 19,
+    Local4 = 20,
+    Local5 = 21,
+    Local6 = 22,
+    Local7 = 23,
+}
+
+/// Sends RFC 5424 messages to a syslog daemon over a Unix datagram socket.
+///
+/// The subsystem becomes the APP-NAME and the category the MSGID. These
+/// header fields are limited to short printable ASCII, so `with_sd_id` can
+/// also include them untruncated as structured data.
+#[derive(Clone, Debug)]
+pub struct SyslogBackend {
+    socket: Arc<UnixDatagram>,
+    path: PathBuf,
+    hostname: String,
+    facility: Facility,
+    sd_id: Option<String>,
+}
+
+impl SyslogBackend {
+    pub const DEFAULT_PATH: &'static str = "/dev/log";
+
+    pub fn new() -> io::Result<Self> {
+        Ok(Self {
+            socket: Arc::new(UnixDatagram::unbound()?),
+            path: PathBuf::from(Self::DEFAULT_PATH),
+            hostname: String::from("-"),
+            facility: Facility::default(),
+            sd_id: None,
+        })
+    }
+
+    /// Sets the socket messages are sent to. Defaults to `/dev/log`.
+    pub fn with_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.path = path.as_ref().to_owned();
+        self
+    }
+
+    /// Sets the HOSTNAME field. By default it's left as the nil value and
+    /// filled in by the daemon.
+    pub fn with_hostname(mut self, hostname: &str) -> Self {
+        self.hostname = header_field(hostname, 255);
+        self
+    }
+
+    pub fn with_facility(mut self, facility: Facility) -> Self {
+        self.facility = facility;
+        self
+    }
+
+    /// Adds a structured data element with this SD-ID to every message, with
+    /// the subsystem and category as its `subsystem` and `category`
+    /// parameters. RFC 5424 requires custom SD-IDs to end in `@` and an IANA
+    /// private enterprise number, e.g. `oslog@12345`, so there's no default
+    /// and messages have no structured data unless it's set.
+    pub fn with_sd_id(mut self, sd_id: &str) -> Self {
+        self.sd_id = Some(sd_name(sd_id));
+        self
+    }
+}
+
+impl Backend for SyslogBackend {
+    fn create(&self, subsystem: &CStr, category: &CStr) -> Result<Box<dyn Handle>, Error> {
+        let subsystem = subsystem.to_string_lossy();
+        let category = category.to_string_lossy();
+
+        let structured_data = match &self.sd_id {
+            Some(sd_id) => format!(
+                "[{} subsystem=\"{}\" category=\"{}\"]",
+                sd_id,
+                param_value(&subsystem),
+                param_value(&category)
+            ),
+            None => String::from("-"),
+        };
+
+        // Everything after the timestamp is fixed for the lifetime of the log.
+        let header = format!(
+            "{} {} {} {} {}",
+            self.hostname,
+            header_field(&subsystem, 48),
+            std::process::id(),
+            header_field(&category, 32),
+            structured_data
+        );
+
+        Ok(Box::new(SyslogHandle {
+            backend: self.clone(),
+            header,
+        }))
+    }
+
+    fn global(&self) -> Result<Box<dyn Handle>, Error> {
+        self.create(c"", c"")
+    }
+}
+
+#[derive(Clone)]
+struct SyslogHandle {
+    backend: SyslogBackend,
+    header: String,
+}
+
+impl SyslogHandle {
+    fn format(&self, level: Level, message: &CStr, time: SystemTime) -> Vec<u8> {
+        let prival = self.backend.facility as u8 * 8 + severity(level);
+        let mut packet = format!("<{}>1 {} {} ", prival, rfc3339(time), self.header).into_bytes();
+        // MSG is marked as UTF-8 with a BOM.
+        packet.extend_from_slice(b"\xEF\xBB\xBF");
+        packet.extend_from_slice(message.to_string_lossy().as_bytes());
+        packet
+    }
+}
+
+impl Handle for SyslogHandle {
+    fn clone_handle(&self) -> Box<dyn Handle> {
+        Box::new(self.clone())
+    }
+
+    fn log(&self, level: Level, message: &CStr) {
+        let packet = self.format(level, message, SystemTime::now());
+        // There's nowhere to report failures, e.g. when no daemon is running.
+        let _ = self.backend.socket.send_to(&packet, &self.backend.path);
+    }
+
+    fn level_is_enabled(&self, _level: Level) -> bool {
+        true
+    }
+}
+
+pub(super) fn severity(level: Level) -> u8 {
+    match level {
+        Level::Debug => 7,
+        Level::Info => 6,
+        Level::Default => 5,
+        Level::Error => 3,
+        Level::Fault => 2,
+    }
+}
+
+/// Header fields are limited to printable ASCII without spaces, and use `-`
+/// when empty.
+fn header_field(value: &str, max_len: usize) -> String {
+    if value.is_empty() {
+        return String::from("-");
+    }
+
+    value
+        .chars()
+        .map(|c| if c.is_ascii_graphic() { c } else { '_' })
+        .take(max_len)
+        .collect()
+}
+
+/// SD-IDs are also limited to 32 characters, and can't contain `=`, `]` or
+/// `"`.
+fn sd_name(value: &str) -> String {
+    header_field(value, 32).replace(['=', ']', '"'], "_")
+}
+
+fn param_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn socket_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("oslog-{}-{}.sock", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_header_fields() {
+        assert_eq!(header_field("", 48), "-");
+        assert_eq!(header_field("Data base\u{1F601}", 48), "Data_base_");
+        assert_eq!(header_field("abcdef", 3), "abc");
+        assert_eq!(param_value(r#"a"b\c]d"#), r#"a\"b\\c\]d"#);
+        assert_eq!(sd_name("a=b]c\"d e@1"), "a_b_c_d_e@1");
+    }
+
+    #[test]
+    fn test_send() {
+        let path = socket_path("syslog");
+        let server = UnixDatagram::bind(&path).unwrap();
+
+        let backend = SyslogBackend::new()
+            .unwrap()
+            .with_path(&path)
+            .with_hostname("host")
+            .with_facility(Facility::Local3);
+        let structured = backend.clone().with_sd_id("oslog@12345");
+        for backend in [&backend, &structured] {
+            let log = crate::OsLog::with_backend(backend, "com.example.test", "Data base");
+            log.error("Hello \u{1F601}");
+        }
+
+        let mut buf = [0; 1024];
+        let mut receive = || {
+            let len = server.recv(&mut buf).unwrap();
+            let packet = String::from_utf8(buf[..len].to_vec()).unwrap();
+
+            // local3 * 8 + err
+            assert!(packet.starts_with("<155>1 "), "{}", packet);
+            let (_, rest) = packet.split_once(' ').unwrap();
+            let (_, rest) = rest.split_once(' ').unwrap();
+            rest.to_owned()
+        };
+        assert_eq!(
+            receive(),
+            format!(
+                "host com.example.test {} Data_base - \u{FEFF}Hello \u{1F601}",
+                std::process::id()
+            )
+        );
+        assert_eq!(
+            receive(),
+            format!(
+                "host com.example.test {} Data_base [oslog@12345 subsystem=\"com.example.test\" category=\"Data base\"] \u{FEFF}Hello \u{1F601}",
+                std::process::id()
+            )
+        );
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_timestamp_and_severity() {
+        let backend = SyslogBackend::new().unwrap();
+        let handle = SyslogHandle {
+            header: String::from("- - 1 - -"),
+            backend,
+        };
+        let time = UNIX_EPOCH + Duration::from_secs(86_400);
+        assert_eq!(
+            handle.format(Level::Debug, c"m", time),
+            b"<15>1 1970-01-02T00:00:00.000000Z - - 1 - - \xEF\xBB\xBFm"
+        );
+        assert_eq!(&handle.format(Level::Fault, c"m", time)[..4], b"<10>");
+    }
+}
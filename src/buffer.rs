@@ -0,0 +1,258 @@
+use crate::sanitize::Sanitizer;
+use crate::{ControlChars, NulPolicy};
+use std::ffi::CStr;
+use std::fmt;
+
+/// Messages shorter than this are built on the stack.
+pub(crate) const STACK_CAPACITY: usize = 512;
+
+/// Builds a nul terminated message, only allocating once it outgrows
+/// `STACK_CAPACITY`. Nul bytes are handled according to the `NulPolicy`, and
+/// control characters according to `ControlChars`.
+pub(crate) struct MessageBuffer {
+    stack: [u8; STACK_CAPACITY],
+    len: usize,
+    // Empty, and so unallocated, until the message outgrows the stack.
+    heap: Vec<u8>,
+    policy: NulPolicy,
+    // Where the first nul byte was, once one has been truncated or rejected.
+    nul_position: Option<usize>,
+    sanitizer: Sanitizer,
+}
+
+impl MessageBuffer {
+    #[inline]
+    pub(crate) fn new(policy: NulPolicy) -> Self {
+        Self {
+            stack: [0; STACK_CAPACITY],
+            len: 0,
+            heap: Vec::new(),
+            policy,
+            nul_position: None,
+            sanitizer: Sanitizer::new(ControlChars::Keep),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn with_control_chars(mut self, control_chars: ControlChars) -> Self {
+        self.sanitizer = Sanitizer::new(control_chars);
+        self
+    }
+
+    #[inline]
+    pub(crate) fn push_str(&mut self, s: &str) {
+        if self.sanitizer.is_active() {
+            let mut sanitizer = self.sanitizer;
+            sanitizer.sanitize(s, |text| self.push_text(text));
+            self.sanitizer = sanitizer;
+        } else {
+            self.push_text(s);
+        }
+    }
+
+    #[inline]
+    fn push_text(&mut self, s: &str) {
+        if self.nul_position.is_some() {
+            return;
+        }
+
+        let Some(replacement) = self.policy.replacement() else {
+            let chunk = match s.find('\0') {
+                Some(index) => {
+                    self.nul_position = Some(self.as_str().len() + index);
+                    &s[..index]
+                }
+                None => s,
+            };
+            return self.push_bytes(chunk.as_bytes());
+        };
+
+        for (i, chunk) in s.split('\0').enumerate() {
+            if i > 0 {
+                for part in replacement.split('\0') {
+                    self.push_bytes(part.as_bytes());
+                }
+            }
+            self.push_bytes(chunk.as_bytes());
+        }
+    }
+
+    /// The position of the first nul byte if the policy rejects the message.
+    #[inline]
+    pub(crate) fn rejected(&self) -> Option<usize> {
+        self.nul_position
+            .filter(|_| self.policy == NulPolicy::Reject)
+    }
+
+    /// The message so far.
+    #[inline]
+    pub(crate) fn as_str(&self) -> &str {
+        let bytes = if self.heap.is_empty() {
+            &self.stack[..self.len]
+        } else {
+            &self.heap[..]
+        };
+        // SAFETY: only whole strings and the nul terminator are pushed.
+        unsafe { std::str::from_utf8_unchecked(bytes) }
+    }
+
+    #[inline]
+    pub(crate) fn as_cstr(&mut self) -> &CStr {
+        self.push_bytes(b"\0");
+        let bytes = if self.heap.is_empty() {
+            &self.stack[..self.len]
+        } else {
+            &self.heap[..]
+        };
+        CStr::from_bytes_with_nul(bytes).expect("nul bytes are replaced")
+    }
+
+    #[inline]
+    fn push_bytes(&mut self, bytes: &[u8]) {
+        if !self.heap.is_empty() {
+            self.heap.extend_from_slice(bytes);
+        } else if self.len + bytes.len() <= STACK_CAPACITY {
+            self.stack[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+            self.len += bytes.len();
+        } else {
+            self.heap.reserve(self.len + bytes.len() + 1);
+            self.heap.extend_from_slice(&self.stack[..self.len]);
+            self.heap.extend_from_slice(bytes);
+        }
+    }
+}
+
+impl fmt::Write for MessageBuffer {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+    use std::fmt::Write;
+
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            ALLOCATIONS.with(|count| count.set(count.get() + 1));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            ALLOCATIONS.with(|count| count.set(count.get() + 1));
+            System.realloc(ptr, layout, new_size)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    /// Counts the allocations made by the current thread while running `f`.
+    pub(crate) fn allocations<F: FnOnce()>(f: F) -> usize {
+        let before = ALLOCATIONS.with(Cell::get);
+        f();
+        ALLOCATIONS.with(Cell::get) - before
+    }
+
+    #[test]
+    fn test_short_message_stays_on_stack() {
+        let count = allocations(|| {
+            let mut buffer = MessageBuffer::new(NulPolicy::default());
+            buffer.push_str("Hello\0there ");
+            let _ = write!(buffer, "{} {:?}", 42, "\u{1F601}");
+            assert_eq!(
+                buffer.as_cstr().to_bytes(),
+                "Hello(null)there 42 \"\u{1F601}\"".as_bytes()
+            );
+        });
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn test_long_message_moves_to_heap() {
+        let long = "a".repeat(STACK_CAPACITY);
+        let mut buffer = MessageBuffer::new(NulPolicy::default());
+        buffer.push_str("bb");
+        buffer.push_str(&long);
+        buffer.push_str("c");
+
+        let expected = format!("bb{}c", long);
+        assert_eq!(buffer.as_cstr().to_bytes(), expected.as_bytes());
+    }
+
+    #[test]
+    fn test_nul_policies() {
+        let nul = '\0';
+        let message = |policy| {
+            let mut buffer = MessageBuffer::new(policy);
+            buffer.push_str("a\0b");
+            let _ = write!(buffer, "{}c", nul);
+            let rejected = buffer.rejected();
+            (buffer.as_cstr().to_str().unwrap().to_owned(), rejected)
+        };
+
+        assert_eq!(
+            message(NulPolicy::default()),
+            (String::from("a(null)b(null)c"), None)
+        );
+        assert_eq!(
+            message(NulPolicy::Replace("\0-")),
+            (String::from("a-b-c"), None)
+        );
+        assert_eq!(
+            message(NulPolicy::Escape),
+            (String::from("a\\0b\\0c"), None)
+        );
+        assert_eq!(
+            message(NulPolicy::EscapeUnicode),
+            (String::from("a\\u{0}b\\u{0}c"), None)
+        );
+        assert_eq!(message(NulPolicy::Truncate), (String::from("a"), None));
+        assert_eq!(message(NulPolicy::Reject), (String::from("a"), Some(1)));
+
+        let mut buffer = MessageBuffer::new(NulPolicy::Reject);
+        buffer.push_str("abc");
+        buffer.push_str("d\0");
+        assert_eq!(buffer.rejected(), Some(4));
+    }
+
+    #[test]
+    fn test_control_chars() {
+        let mut buffer =
+            MessageBuffer::new(NulPolicy::default()).with_control_chars(ControlChars::Strip);
+        let emoji = '\u{1F601}';
+        let _ = write!(buffer, "\x1b[{}m{}\x1b[0m\0\r", 31, emoji);
+        assert_eq!(buffer.as_cstr().to_str().unwrap(), "\u{1F601}(null)");
+
+        let count = tests::allocations(|| {
+            let mut buffer =
+                MessageBuffer::new(NulPolicy::default()).with_control_chars(ControlChars::Escape);
+            let _ = write!(buffer, "\x1b[{}m", 31);
+            assert_eq!(buffer.as_cstr().to_bytes(), b"\\x1b[31m");
+        });
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn test_exactly_full_stack() {
+        let full = "a".repeat(STACK_CAPACITY);
+        let mut buffer = MessageBuffer::new(NulPolicy::default());
+        buffer.push_str(&full);
+        assert_eq!(buffer.as_cstr().to_bytes(), full.as_bytes());
+    }
+}
@@ -0,0 +1,185 @@
+//! Splitting long and multi-line messages into numbered entries.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// How newlines in a message are handled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Newlines {
+    /// Log the message as it is.
+    #[default]
+    Keep,
+    /// Log each non-empty line as its own entry, prefixed with its number and
+    /// an id shared by the whole message, e.g. `[2/3] [0000002a] ...`. Lines
+    /// end at `\n`, `\r\n` or `\r`, and a message with only one line is
+    /// logged without a prefix.
+    Split,
+    /// Replace newlines and carriage returns with `\n` and `\r`, keeping the
+    /// message on one line.
+    Escape,
+}
+
+/// Replaces `\n` and `\r` with their escapes.
+pub(crate) fn escape_newlines(message: &str) -> String {
+    message.replace('\n', "\\n").replace('\r', "\\r")
+}
+
+/// Splits `message` at `\r\n`, `\n` and lone `\r`. Like `str::lines`, a
+/// trailing line ending doesn't produce an empty last line.
+pub(crate) fn lines(message: &str) -> impl Iterator<Item = &str> {
+    let mut rest = Some(message).filter(|m| !m.is_empty());
+    std::iter::from_fn(move || {
+        let text = rest?;
+        let Some(index) = text.find(['\n', '\r']) else {
+            rest = None;
+            return Some(text);
+        };
+
+        let len = if text[index..].starts_with("\r\n") {
+            2
+        } else {
+            1
+        };
+        rest = Some(&text[index + len..]).filter(|tail| !tail.is_empty());
+        Some(&text[..index])
+    })
+}
+
+/// Returns an id shared by the chunks of one message, unique within the
+/// process.
+pub(crate) fn next_id() -> u32 {
+    static NEXT: AtomicU32 = AtomicU32::new(1);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Splits `message` into pieces of at most `max_len` bytes without breaking
+/// up characters. A piece holds at least one character, even if it's longer
+/// than `max_len`.
+pub(crate) fn split(message: &str, max_len: usize) -> Vec<&str> {
+    let mut chunks = Vec::with_capacity(message.len() / max_len.max(1) + 1);
+    let mut rest = message;
+
+    while !rest.is_empty() {
+        let mut end = max_len.min(rest.len());
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        if end == 0 {
+            end = rest.chars().next().map_or(rest.len(), char::len_utf8);
+        }
+
+        let (chunk, tail) = rest.split_at(end);
+        chunks.push(chunk);
+        rest = tail;
+    }
+
+    chunks
+}
+
+/// Splits the non-empty `lines` into pieces which fit in `max_len` bytes once
+/// prefixed with their number and id. A message that fits in one piece isn't
+/// prefixed, so it can use all of `max_len`.
+pub(crate) fn pieces<'a>(lines: impl IntoIterator<Item = &'a str>, max_len: usize) -> Vec<&'a str> {
+    let lines: Vec<_> = lines.into_iter().filter(|line| !line.is_empty()).collect();
+    if let [line] = lines[..] {
+        if line.len() <= max_len {
+            return vec![line];
+        }
+    }
+
+    // The prefix grows with the number of pieces, which depends on how much
+    // room the prefix leaves.
+    let mut digits = 1;
+    loop {
+        let max_len = max_len.saturating_sub(prefix_len(digits));
+        let pieces: Vec<_> = lines.iter().flat_map(|line| split(line, max_len)).collect();
+        if pieces.len().to_string().len() <= digits {
+            return pieces;
+        }
+        digits += 1;
+    }
+}
+
+/// The length of `[i/n] [0000002a] ` when `n` has `digits` digits.
+fn prefix_len(digits: usize) -> usize {
+    2 * digits + 15
+}
+
+/// Strips the `[1/2] [0000002a] ` prefix from each of `messages`.
+#[cfg(test)]
+pub(crate) fn unprefixed(messages: impl IntoIterator<Item = String>) -> Vec<String> {
+    messages
+        .into_iter()
+        .map(|message| {
+            let (_, text) = message
+                .strip_prefix('[')
+                .and_then(|rest| rest.split_once("] ["))
+                .and_then(|(_, rest)| rest.split_once("] "))
+                .unwrap_or_else(|| panic!("{message:?} isn't prefixed"));
+            text.to_owned()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_ascii() {
+        assert_eq!(split("abcdefg", 3), ["abc", "def", "g"]);
+        assert_eq!(split("abcdef", 3), ["abc", "def"]);
+        assert_eq!(split("abc", 10), ["abc"]);
+        assert!(split("", 3).is_empty());
+    }
+
+    #[test]
+    fn test_split_on_char_boundaries() {
+        // "é" is 2 bytes and the emoji 4.
+        assert_eq!(split("aéé", 2), ["a", "é", "é"]);
+        assert_eq!(split("\u{1F601}\u{1F601}a", 5), ["\u{1F601}", "\u{1F601}a"]);
+        assert_eq!(split("\u{1F601}\u{1F601}", 2), ["\u{1F601}", "\u{1F601}"]);
+        assert_eq!(split("ab", 0), ["a", "b"]);
+    }
+
+    #[test]
+    fn test_escape_newlines() {
+        assert_eq!(escape_newlines("a\r\nb\n"), "a\\r\\nb\\n");
+        assert_eq!(escape_newlines("\u{1F601}"), "\u{1F601}");
+    }
+
+    #[test]
+    fn test_lines() {
+        let lines = |message| lines(message).collect::<Vec<_>>();
+        assert_eq!(lines("a\rb\r\nc\n\nd"), ["a", "b", "c", "", "d"]);
+        assert_eq!(lines("a\n"), ["a"]);
+        assert_eq!(lines("a\r"), ["a"]);
+        assert_eq!(lines("\n"), [""]);
+        assert!(lines("").is_empty());
+    }
+
+    #[test]
+    fn test_pieces_leave_room_for_the_prefix() {
+        assert_eq!(pieces(["abcde"], 5), ["abcde"]);
+        assert_eq!(pieces(["", "abc", ""], 3), ["abc"]);
+        assert_eq!(pieces(["a", "b"], 20), ["a", "b"]);
+        assert_eq!(
+            pieces(["abcdefghijklmnopqrst"], 20),
+            ["abcdefghijklmnopqrst"]
+        );
+        assert_eq!(
+            pieces(["abcdefghijklmnopqrstu"], 20),
+            ["abc", "def", "ghi", "jkl", "mno", "pqr", "stu"]
+        );
+
+        // 44 pieces with a one digit count, but 48 with two.
+        let message = "x".repeat(1000);
+        let pieces = pieces([message.as_str()], 40);
+        assert_eq!(pieces.len(), 48);
+        assert!(pieces.iter().all(|piece| piece.len() + prefix_len(2) <= 40));
+    }
+
+    #[test]
+    fn test_ids_are_unique() {
+        assert_ne!(next_id(), next_id());
+    }
+}
@@ -0,0 +1,197 @@
+//! Builds the argument buffer passed to `_os_log_impl`, which is what the
+//! `os_log` family of C macros expand to.
+//!
+//! The buffer starts with a summary byte and the argument count, followed by
+//! each argument as a descriptor byte, a size byte and the argument's bytes.
+//! The descriptor's high nibble is the argument's type and its low nibble the
+//! privacy flags.
+
+use std::ffi::CStr;
+use std::marker::PhantomData;
+
+/// Whether an argument is visible in the unified log, or shown as
+/// `<private>` unless private data logging is enabled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Privacy {
+    Public,
+    Private,
+}
+
+const SUMMARY_HAS_PRIVATE: u8 = 0x01;
+const SUMMARY_HAS_NON_SCALAR: u8 = 0x02;
+
+const TYPE_SCALAR: u8 = 0x00;
+const TYPE_STRING: u8 = 0x20;
+
+const FLAG_PRIVATE: u8 = 0x01;
+const FLAG_PUBLIC: u8 = 0x02;
+
+/// The number of arguments an [`Encoder`] can hold.
+pub const MAX_ARGUMENTS: usize = 32;
+
+// Each argument needs at most a descriptor, a size and 8 bytes of data.
+const CAPACITY: usize = 2 + MAX_ARGUMENTS * 10;
+
+/// A fixed size, stack allocated argument buffer.
+///
+/// Arguments past [`MAX_ARGUMENTS`] are dropped, in which case the unified log
+/// shows them as missing data rather than misreading the buffer. Strings are
+/// encoded as pointers, so the lifetime ties the encoder to them.
+#[derive(Clone)]
+pub struct Encoder<'a> {
+    buffer: [u8; CAPACITY],
+    len: usize,
+    strings: PhantomData<&'a CStr>,
+}
+
+impl Default for Encoder<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> Encoder<'a> {
+    pub fn new() -> Self {
+        Self {
+            buffer: [0; CAPACITY],
+            len: 2,
+            strings: PhantomData,
+        }
+    }
+
+    pub fn push_str(&mut self, value: &'a CStr, privacy: Privacy) -> &mut Self {
+        let pointer = value.as_ptr() as usize as u64;
+        self.push(TYPE_STRING, privacy, &pointer.to_le_bytes())
+    }
+
+    pub fn push_i32(&mut self, value: i32, privacy: Privacy) -> &mut Self {
+        self.push(TYPE_SCALAR, privacy, &value.to_le_bytes())
+    }
+
+    pub fn push_u32(&mut self, value: u32, privacy: Privacy) -> &mut Self {
+        self.push(TYPE_SCALAR, privacy, &value.to_le_bytes())
+    }
+
+    pub fn push_i64(&mut self, value: i64, privacy: Privacy) -> &mut Self {
+        self.push(TYPE_SCALAR, privacy, &value.to_le_bytes())
+    }
+
+    pub fn push_u64(&mut self, value: u64, privacy: Privacy) -> &mut Self {
+        self.push(TYPE_SCALAR, privacy, &value.to_le_bytes())
+    }
+
+    pub fn push_f64(&mut self, value: f64, privacy: Privacy) -> &mut Self {
+        self.push(TYPE_SCALAR, privacy, &value.to_le_bytes())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buffer[..self.len]
+    }
+
+    fn push(&mut self, kind: u8, privacy: Privacy, data: &[u8]) -> &mut Self {
+        if usize::from(self.buffer[1]) == MAX_ARGUMENTS {
+            return self;
+        }
+
+        let flag = match privacy {
+            Privacy::Public => FLAG_PUBLIC,
+            Privacy::Private => {
+                self.buffer[0] |= SUMMARY_HAS_PRIVATE;
+                FLAG_PRIVATE
+            }
+        };
+        if kind != TYPE_SCALAR {
+            self.buffer[0] |= SUMMARY_HAS_NON_SCALAR;
+        }
+        self.buffer[1] += 1;
+
+        let end = self.len + 2 + data.len();
+        self.buffer[self.len] = kind | flag;
+        self.buffer[self.len + 1] = data.len() as u8;
+        self.buffer[self.len + 2..end].copy_from_slice(data);
+        self.len = end;
+
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pointer(value: &CStr) -> [u8; 8] {
+        (value.as_ptr() as usize as u64).to_le_bytes()
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(Encoder::new().as_bytes(), [0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_public_string() {
+        let message = c"Hello";
+        let mut encoder = Encoder::new();
+        encoder.push_str(message, Privacy::Public);
+
+        let mut expected = vec![0x02, 0x01, 0x22, 0x08];
+        expected.extend_from_slice(&pointer(message));
+        assert_eq!(encoder.as_bytes(), expected);
+    }
+
+    #[test]
+    fn test_private_string() {
+        let message = c"Hello";
+        let mut encoder = Encoder::new();
+        encoder.push_str(message, Privacy::Private);
+
+        let mut expected = vec![0x03, 0x01, 0x21, 0x08];
+        expected.extend_from_slice(&pointer(message));
+        assert_eq!(encoder.as_bytes(), expected);
+    }
+
+    #[test]
+    fn test_scalars() {
+        let mut encoder = Encoder::new();
+        encoder
+            .push_i32(-2, Privacy::Public)
+            .push_u64(0x0102_0304_0506_0708, Privacy::Private)
+            .push_f64(1.0, Privacy::Public);
+
+        assert_eq!(
+            encoder.as_bytes(),
+            [
+                0x01, 0x03, //
+                0x02, 0x04, 0xfe, 0xff, 0xff, 0xff, //
+                0x01, 0x08, 0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01, //
+                0x02, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xf0, 0x3f,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mixed() {
+        let name = c"name";
+        let mut encoder = Encoder::new();
+        encoder
+            .push_u32(7, Privacy::Public)
+            .push_str(name, Privacy::Private);
+
+        let mut expected = vec![0x03, 0x02, 0x02, 0x04, 0x07, 0x00, 0x00, 0x00, 0x21, 0x08];
+        expected.extend_from_slice(&pointer(name));
+        assert_eq!(encoder.as_bytes(), expected);
+    }
+
+    #[test]
+    fn test_drops_arguments_past_capacity() {
+        let mut encoder = Encoder::new();
+        for i in 0..MAX_ARGUMENTS as u64 + 5 {
+            encoder.push_u64(i, Privacy::Public);
+        }
+
+        let bytes = encoder.as_bytes();
+        assert_eq!(usize::from(bytes[1]), MAX_ARGUMENTS);
+        assert_eq!(bytes.len(), CAPACITY);
+        assert_eq!(bytes[CAPACITY - 8], MAX_ARGUMENTS as u8 - 1);
+    }
+}
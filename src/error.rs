@@ -0,0 +1,40 @@
+use std::fmt;
+
+/// Why an [`OsLog`](crate::OsLog) couldn't be created or a message couldn't be
+/// logged.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The subsystem contains a nul byte at the given position.
+    InvalidSubsystem(usize),
+    /// The category contains a nul byte at the given position.
+    InvalidCategory(usize),
+    /// The message contains a nul byte at the given position and the log's
+    /// `NulPolicy` rejects it.
+    InvalidMessage(usize),
+    /// The backend returned a null log handle.
+    NullHandle,
+    /// The backend isn't available on this platform.
+    UnsupportedPlatform,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidSubsystem(position) => {
+                write!(f, "subsystem contains a nul byte at position {}", position)
+            }
+            Self::InvalidCategory(position) => {
+                write!(f, "category contains a nul byte at position {}", position)
+            }
+            Self::InvalidMessage(position) => {
+                write!(f, "message contains a nul byte at position {}", position)
+            }
+            Self::NullHandle => f.write_str("unexpected null log handle"),
+            Self::UnsupportedPlatform => {
+                f.write_str("logging backend unsupported on this platform")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -1,8 +1,40 @@
+#[macro_use]
+mod macros;
+
+pub mod backend;
+mod buffer;
+mod chunk;
+pub mod encoder;
+mod error;
+mod mask;
+mod nul;
+mod placeholder;
+mod privacy;
+#[cfg(feature = "redaction")]
+mod redact;
+mod registry;
+mod sanitize;
 mod sys;
+#[cfg(feature = "logger")]
+mod template;
+mod thread;
+
+pub mod testing;
 
 #[cfg(feature = "logger")]
 mod logger;
 
+#[cfg(feature = "macros")]
+pub use oslog_macros::{os_log_static, OsLogValue};
+
+pub use chunk::Newlines;
+pub use encoder::Privacy;
+pub use error::Error;
+pub use mask::HashMask;
+pub use nul::NulPolicy;
+pub use privacy::{Hashed, Private, Public, Sensitive};
+pub use sanitize::ControlChars;
+
 #[cfg(feature = "logger")]
 pub use logger::OsLogger;
 
@@ -12,16 +44,130 @@ pub use logger::init_once;
 #[cfg(feature = "logger")]
 pub use logger::Config;
 
+#[cfg(feature = "redaction")]
+pub use redact::RedactionPreset;
+
+use crate::backend::{Argument, Backend, Handle, Part, StaticFormat};
+use crate::buffer::MessageBuffer;
 use crate::sys::*;
-use std::ffi::{c_void, CString};
+use std::borrow::Cow;
+use std::ffi::{CStr, CString};
+use std::fmt::{self, Write};
+
+/// Used by code generated by `oslog-macros` and `os_log!`.
+#[doc(hidden)]
+pub mod __private {
+    use std::ffi::CString;
+    use std::fmt::Display;
+
+    /// Formats an `os_log_static!` argument, handling control characters and
+    /// nul bytes according to `log`'s settings. Returns `None` if its
+    /// `NulPolicy` rejects it.
+    pub fn to_cstring(log: &super::OsLog, value: &dyn Display) -> Option<CString> {
+        let text = value.to_string();
+        log.nul_policy.apply(&log.control_chars.apply(&text)).ok()
+    }
+
+    /// Panics unless every placeholder in `format` is `{}`. `os_log!` calls
+    /// it in a constant, so other placeholders are compile errors.
+    pub const fn check_os_log_format(format: &str) {
+        let bytes = format.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            let next = if i + 1 < bytes.len() { bytes[i + 1] } else { 0 };
+            match (bytes[i], next) {
+                (b'{', b'{') | (b'{', b'}') | (b'}', b'}') => i += 2,
+                (b'{', _) => panic!("os_log! only supports `{{}}` placeholders"),
+                _ => i += 1,
+            }
+        }
+    }
+}
+
+/// A subsystem or category name accepted by [`OsLog::new`]. Strings are
+/// copied with nul bytes handled by the default `NulPolicy`, while C strings
+/// are used as they are. Use [`OsLog::try_with_nul_policy`] to choose another
+/// policy.
+pub trait AsCStr {
+    fn as_cstr(&self) -> Cow<'_, CStr>;
+}
+
+impl AsCStr for str {
+    #[inline]
+    fn as_cstr(&self) -> Cow<'_, CStr> {
+        // The default policy replaces nul bytes, so it can't fail.
+        Cow::Owned(NulPolicy::default().apply(self).unwrap())
+    }
+}
+
+impl AsCStr for String {
+    #[inline]
+    fn as_cstr(&self) -> Cow<'_, CStr> {
+        self.as_str().as_cstr()
+    }
+}
+
+impl AsCStr for CStr {
+    #[inline]
+    fn as_cstr(&self) -> Cow<'_, CStr> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl AsCStr for CString {
+    #[inline]
+    fn as_cstr(&self) -> Cow<'_, CStr> {
+        Cow::Borrowed(self)
+    }
+}
 
-#[inline]
-fn to_cstr(message: &str) -> CString {
-    let fixed = message.replace('\0', "(null)");
-    CString::new(fixed).unwrap()
+impl<T: AsCStr + ?Sized> AsCStr for &T {
+    #[inline]
+    fn as_cstr(&self) -> Cow<'_, CStr> {
+        (**self).as_cstr()
+    }
+}
+
+/// Splits `format` into runs of public and private text, replacing each `{}`
+/// with the next argument. Adjacent runs always differ in privacy.
+fn split_privacy(format: &str, args: &[(Privacy, &dyn fmt::Display)]) -> Vec<(Privacy, String)> {
+    let mut parts: Vec<(Privacy, String)> = Vec::new();
+    let mut push = |privacy: Privacy, text: &dyn fmt::Display| match parts.last_mut() {
+        Some((last, buffer)) if *last == privacy => {
+            let _ = write!(buffer, "{}", text);
+        }
+        _ => parts.push((privacy, text.to_string())),
+    };
+
+    let mut args = args.iter();
+    let mut rest = format;
+    while let Some(index) = rest.find(['{', '}']) {
+        let (text, tail) = rest.split_at(index);
+        if !text.is_empty() {
+            push(Privacy::Public, &text);
+        }
+
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            push(Privacy::Public, &&tail[..1]);
+            rest = &tail[2..];
+        } else if let (Some(end), Some((privacy, arg))) = (tail.find('}'), args.next()) {
+            push(*privacy, arg);
+            rest = &tail[end + 1..];
+        } else {
+            push(Privacy::Public, &tail);
+            rest = "";
+        }
+    }
+
+    if !rest.is_empty() {
+        push(Privacy::Public, &rest);
+    }
+
+    parts
 }
 
 #[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Level {
     Debug = OS_LOG_TYPE_DEBUG,
     Info = OS_LOG_TYPE_INFO,
@@ -30,6 +176,19 @@ pub enum Level {
     Fault = OS_LOG_TYPE_FAULT,
 }
 
+impl Level {
+    /// The name of the level as used by Apple's `log` tool.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Debug => "Debug",
+            Self::Info => "Info",
+            Self::Default => "Default",
+            Self::Error => "Error",
+            Self::Fault => "Fault",
+        }
+    }
+}
+
 #[cfg(feature = "logger")]
 impl From<log::Level> for Level {
     fn from(other: log::Level) -> Self {
@@ -44,83 +203,615 @@ impl From<log::Level> for Level {
 }
 
 pub struct OsLog {
-    inner: os_log_t,
+    inner: Box<dyn Handle>,
+    chunk_size: Option<usize>,
+    nul_policy: NulPolicy,
+    control_chars: ControlChars,
+    hash_mask: Option<HashMask>,
+    privacy: Privacy,
 }
 
-unsafe impl Send for OsLog {}
-unsafe impl Sync for OsLog {}
-
-impl Drop for OsLog {
-    fn drop(&mut self) {
-        unsafe {
-            if self.inner != wrapped_get_default_log() {
-                os_release(self.inner as *mut c_void);
-            }
+/// Clones refer to the same underlying log.
+impl Clone for OsLog {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone_handle(),
+            chunk_size: self.chunk_size,
+            nul_policy: self.nul_policy,
+            control_chars: self.control_chars,
+            hash_mask: self.hash_mask,
+            privacy: self.privacy,
         }
     }
 }
 
 impl OsLog {
+    /// Creates a log, replacing any nul bytes in `subsystem` and `category`.
+    /// Either may be a `&str` or a `&CStr`, which is passed on without
+    /// copying.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the platform's backend can't create the log. Use `try_new`
+    /// to handle that instead.
     #[inline]
-    pub fn new(subsystem: &str, category: &str) -> Self {
-        let subsystem = to_cstr(subsystem);
-        let category = to_cstr(category);
+    pub fn new(subsystem: impl AsCStr, category: impl AsCStr) -> Self {
+        Self::with_backend(backend::platform(), subsystem, category)
+    }
 
-        let inner = unsafe { os_log_create(subsystem.as_ptr(), category.as_ptr()) };
+    /// Like `new`, but messages are written through `backend` rather than the
+    /// platform's default.
+    #[inline]
+    pub fn with_backend(
+        backend: &dyn Backend,
+        subsystem: impl AsCStr,
+        category: impl AsCStr,
+    ) -> Self {
+        match Self::from_cstrs(backend, &subsystem.as_cstr(), &category.as_cstr()) {
+            Ok(log) => log,
+            Err(err) => panic!("Failed to create log: {}", err),
+        }
+    }
 
-        assert!(!inner.is_null(), "Unexpected null value from os_log_create");
+    /// Creates a log, failing instead of panicking. Unlike `new`, nul bytes in
+    /// `subsystem` or `category` are an error.
+    #[inline]
+    pub fn try_new(subsystem: &str, category: &str) -> Result<Self, Error> {
+        Self::try_with_backend(backend::platform(), subsystem, category)
+    }
 
-        Self { inner }
+    /// Like `try_new`, but messages are written through `backend` rather than
+    /// the platform's default.
+    #[inline]
+    pub fn try_with_backend(
+        backend: &dyn Backend,
+        subsystem: &str,
+        category: &str,
+    ) -> Result<Self, Error> {
+        let subsystem =
+            CString::new(subsystem).map_err(|e| Error::InvalidSubsystem(e.nul_position()))?;
+        let category =
+            CString::new(category).map_err(|e| Error::InvalidCategory(e.nul_position()))?;
+
+        Self::from_cstrs(backend, &subsystem, &category)
+    }
+
+    /// Like `try_with_backend`, but handles nul bytes in `subsystem`,
+    /// `category` and every message according to `policy`.
+    #[inline]
+    pub fn try_with_nul_policy(
+        backend: &dyn Backend,
+        subsystem: &str,
+        category: &str,
+        policy: NulPolicy,
+    ) -> Result<Self, Error> {
+        let subsystem = policy.apply(subsystem).map_err(Error::InvalidSubsystem)?;
+        let category = policy.apply(category).map_err(Error::InvalidCategory)?;
+
+        Ok(Self::from_cstrs(backend, &subsystem, &category)?.with_nul_policy(policy))
     }
 
+    #[inline]
+    fn from_cstrs(backend: &dyn Backend, subsystem: &CStr, category: &CStr) -> Result<Self, Error> {
+        let inner = backend.create(subsystem, category)?;
+        Ok(Self::from_handle(inner))
+    }
+
+    #[inline]
+    pub(crate) fn from_handle(inner: Box<dyn Handle>) -> Self {
+        Self {
+            inner,
+            chunk_size: None,
+            nul_policy: NulPolicy::default(),
+            control_chars: ControlChars::default(),
+            hash_mask: None,
+            privacy: Privacy::Public,
+        }
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the platform's backend can't provide the default log. Use
+    /// `try_global` to handle that instead.
     #[inline]
     pub fn global() -> Self {
-        let inner = unsafe { wrapped_get_default_log() };
+        match Self::try_global() {
+            Ok(log) => log,
+            Err(err) => panic!("Failed to get the default log: {}", err),
+        }
+    }
+
+    #[inline]
+    pub fn try_global() -> Result<Self, Error> {
+        let inner = backend::platform().global()?;
+        Ok(Self::from_handle(inner))
+    }
+
+    /// Returns the log shared by every caller using the same `subsystem` and
+    /// `category`, creating it with `new` on first use. Shared logs live for
+    /// the rest of the program.
+    #[inline]
+    pub fn shared(subsystem: &str, category: &str) -> Self {
+        match registry::get_or_try_insert(subsystem, category, || {
+            Ok(Self::new(subsystem, category))
+        }) {
+            Ok(log) => log,
+            Err(err) => panic!("Failed to create log: {}", err),
+        }
+    }
+
+    /// Like `shared`, but creates the log with `try_new`.
+    #[inline]
+    pub fn try_shared(subsystem: &str, category: &str) -> Result<Self, Error> {
+        registry::get_or_try_insert(subsystem, category, || Self::try_new(subsystem, category))
+    }
+
+    /// Returns a log which discards everything at next to no cost, for
+    /// switching logging off without passing `Option<OsLog>` around.
+    #[inline]
+    pub fn disabled() -> Self {
+        Self::from_handle(backend::platform().disabled())
+    }
+
+    /// Splits messages longer than `chunk_size` bytes into several entries,
+    /// each prefixed with its number and an id shared by the whole message,
+    /// e.g. `[2/3] [0000002a] ...`. The unified log truncates messages longer
+    /// than about 1 KB, so `Some(1000)` keeps everything. `None`, the default,
+    /// logs messages whole.
+    ///
+    /// Lengths are measured once nul bytes and control characters have been
+    /// replaced, and include the prefix. Applies to every message except
+    /// those logged with the `_cstr` methods and `os_log_static!`. Pieces of
+    /// messages with `Private` values keep their privacy.
+    #[inline]
+    pub fn with_chunk_size(mut self, chunk_size: Option<usize>) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// Sets how nul bytes in messages are handled. By default they're
+    /// replaced with `(null)`. With `NulPolicy::Reject` messages containing
+    /// them are dropped, or reported by `try_with_level`.
+    #[inline]
+    pub fn with_nul_policy(mut self, policy: NulPolicy) -> Self {
+        self.nul_policy = policy;
+        self
+    }
 
-        assert!(!inner.is_null(), "Unexpected null value for OS_DEFAULT_LOG");
+    /// Sets how control characters and ANSI escape sequences in messages are
+    /// handled. By default they're logged as they are.
+    ///
+    /// Messages passed to the `_cstr` methods aren't sanitised, as they're
+    /// logged without copying.
+    #[inline]
+    pub fn with_control_chars(mut self, control_chars: ControlChars) -> Self {
+        self.control_chars = control_chars;
+        self
+    }
+
+    /// Replaces private values with salted hashes, which are logged as
+    /// public, rather than marking them private. `None`, the default, keeps
+    /// them private.
+    #[inline]
+    pub fn with_hash_mask(mut self, hash_mask: Option<HashMask>) -> Self {
+        self.hash_mask = hash_mask;
+        self
+    }
+
+    /// Sets the privacy of messages logged with `with_level`, its per-level
+    /// shorthands and the `_cstr` variants, and of text outside `Private`
+    /// and `Public` values in `log_fmt`. Messages are public by default.
+    #[inline]
+    pub fn with_default_privacy(mut self, privacy: Privacy) -> Self {
+        self.privacy = privacy;
+        self
+    }
 
-        Self { inner }
+    #[inline]
+    fn buffer(&self) -> MessageBuffer {
+        MessageBuffer::new(self.nul_policy).with_control_chars(self.control_chars)
     }
 
+    /// Messages shorter than 512 bytes are passed to the backend without
+    /// allocating.
     #[inline]
     pub fn with_level(&self, level: Level, message: &str) {
-        let message = to_cstr(message);
-        unsafe { wrapped_os_log_with_type(self.inner, level as u8, message.as_ptr()) }
+        let _ = self.write(level, message, Newlines::Keep);
+    }
+
+    /// Like `with_level`, but fails if the log's `NulPolicy` rejects
+    /// `message`.
+    #[inline]
+    pub fn try_with_level(&self, level: Level, message: &str) -> Result<(), Error> {
+        self.write(level, message, Newlines::Keep)
+    }
+
+    /// Like `with_level`, but handles newlines in `message` as `newlines`
+    /// says rather than keeping them.
+    #[inline]
+    pub fn with_newlines(&self, level: Level, message: &str, newlines: Newlines) {
+        let _ = self.write(level, message, newlines);
+    }
+
+    #[inline]
+    fn write(&self, level: Level, message: &str, newlines: Newlines) -> Result<(), Error> {
+        if self.nul_policy == NulPolicy::Reject {
+            if let Some(position) = message.find('\0') {
+                return Err(Error::InvalidMessage(position));
+            }
+        }
+
+        // Messages are measured and split once nul bytes and control
+        // characters have been replaced.
+        let mut buffer = self.buffer();
+        buffer.push_str(message);
+        if !self.log_reshaped(level, buffer.as_str(), newlines) {
+            self.emit(level, buffer.as_cstr());
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn log_str(&self, level: Level, message: &str) {
+        let mut buffer = self.buffer();
+        buffer.push_str(message);
+        self.emit(level, buffer.as_cstr());
+    }
+
+    /// Passes an unmarked message to the backend with the log's default
+    /// privacy.
+    #[inline]
+    fn emit(&self, level: Level, message: &CStr) {
+        match self.privacy {
+            Privacy::Public => self.inner.log(level, message),
+            Privacy::Private => self.emit_private(level, message),
+        }
+    }
+
+    #[cold]
+    fn emit_private(&self, level: Level, message: &CStr) {
+        if let Some(mask) = &self.hash_mask {
+            let masked = CString::new(mask.mask(message.to_bytes())).unwrap();
+            return self.inner.log(level, &masked);
+        }
+
+        let part = Part {
+            privacy: Privacy::Private,
+            text: message,
+        };
+        self.inner.log_parts(level, &[part]);
+    }
+
+    /// Logs `message` if it has to be split or escaped first, returning
+    /// whether it did.
+    #[inline]
+    fn log_reshaped(&self, level: Level, message: &str, newlines: Newlines) -> bool {
+        let has_newlines = newlines != Newlines::Keep && message.contains(['\n', '\r']);
+        let too_long = self.chunk_size.is_some_and(|max| message.len() > max);
+        if !has_newlines && !too_long {
+            return false;
+        }
+
+        let max_len = self.chunk_size.unwrap_or(usize::MAX);
+        match newlines {
+            Newlines::Escape if has_newlines => {
+                let escaped = chunk::escape_newlines(message);
+                if !self.log_reshaped(level, &escaped, Newlines::Keep) {
+                    self.log_str(level, &escaped);
+                }
+            }
+            Newlines::Split if has_newlines => {
+                self.log_pieces(level, &chunk::pieces(chunk::lines(message), max_len))
+            }
+            _ => self.log_pieces(level, &chunk::pieces([message], max_len)),
+        }
+
+        true
+    }
+
+    /// Logs each piece as its own entry, prefixed with its number and an id
+    /// shared by all of them. A single piece is logged without a prefix.
+    #[cold]
+    fn log_pieces(&self, level: Level, pieces: &[&str]) {
+        match pieces {
+            // Only line endings, which are logged as one empty entry like `""`.
+            [] => return self.log_str(level, ""),
+            [piece] => return self.log_str(level, piece),
+            _ => {}
+        }
+
+        let id = chunk::next_id();
+
+        for (i, piece) in pieces.iter().enumerate() {
+            let mut buffer = self.buffer();
+            let _ = write!(buffer, "[{}/{}] [{:08x}] ", i + 1, pieces.len(), id);
+            buffer.push_str(piece);
+            self.emit(level, buffer.as_cstr());
+        }
+    }
+
+    /// Logs `message` as it is, without copying or allocating. On Apple
+    /// platforms the pointer is passed straight to the unified log.
+    #[inline]
+    pub fn with_level_cstr(&self, level: Level, message: &CStr) {
+        self.emit(level, message);
+    }
+
+    /// Formats `args` directly into the message passed to the backend. Nothing
+    /// is formatted if `level` isn't enabled.
+    #[inline]
+    pub fn log_fmt(&self, level: Level, args: fmt::Arguments<'_>) {
+        self.log_fmt_with_newlines(level, args, Newlines::Keep);
+    }
+
+    #[inline]
+    pub(crate) fn log_fmt_with_newlines(
+        &self,
+        level: Level,
+        args: fmt::Arguments<'_>,
+        newlines: Newlines,
+    ) {
+        if self.level_is_enabled(level) {
+            if let Some(message) = args.as_str() {
+                return self.with_newlines(level, message, newlines);
+            }
+
+            let mut buffer = self.buffer();
+            let marked = privacy::capture(self.hash_mask, || {
+                let _ = buffer.write_fmt(args);
+            });
+            if buffer.rejected().is_some() {
+                return;
+            }
+            if marked {
+                return self.log_marked(level, buffer.as_str(), newlines);
+            }
+            if !self.log_reshaped(level, buffer.as_str(), newlines) {
+                self.emit(level, buffer.as_cstr());
+            }
+        }
+    }
+
+    /// Logs a message which was formatted in advance, e.g. to redact it.
+    /// `marked` is whether `Private` or `Public` values wrote markers into it.
+    #[cfg(feature = "redaction")]
+    pub(crate) fn log_preformatted(
+        &self,
+        level: Level,
+        message: &str,
+        marked: bool,
+        newlines: Newlines,
+    ) {
+        if !marked {
+            return self.with_newlines(level, message, newlines);
+        }
+
+        let mut buffer = self.buffer();
+        buffer.push_str(message);
+        if buffer.rejected().is_none() {
+            self.log_marked(level, buffer.as_str(), newlines);
+        }
+    }
+
+    /// Logs `format` with each `{}` replaced by the next argument, marking
+    /// each argument public or private. The format string itself is public.
+    /// Usually called through [`os_log!`].
+    pub fn with_privacy(&self, level: Level, format: &str, args: &[(Privacy, &dyn fmt::Display)]) {
+        if !self.level_is_enabled(level) {
+            return;
+        }
+
+        let mut texts = Vec::new();
+        for (privacy, text) in split_privacy(format, args) {
+            let text = self.control_chars.apply(&text);
+            let Ok(cstr) = self.nul_policy.apply(&text) else {
+                return;
+            };
+            texts.push((privacy, cstr));
+
+            if self.nul_policy == NulPolicy::Truncate && text.contains('\0') {
+                break;
+            }
+        }
+
+        self.log_texts(level, &texts, Newlines::Keep);
+    }
+
+    /// Logs a message containing `Private` or `Public` values, which has
+    /// already been sanitised.
+    #[cold]
+    fn log_marked(&self, level: Level, message: &str, newlines: Newlines) {
+        let texts: Vec<_> = privacy::split_marked(message, self.privacy)
+            .into_iter()
+            .map(|(privacy, text)| (privacy, CString::new(text).unwrap()))
+            .collect();
+
+        self.log_texts(level, &texts, newlines);
+    }
+
+    /// Logs runs of public and private text, handling newlines in them as
+    /// `newlines` says. Hashes are masked first, so they're of the original
+    /// values.
+    fn log_texts(&self, level: Level, texts: &[(Privacy, CString)], newlines: Newlines) {
+        let masked;
+        let texts = match &self.hash_mask {
+            Some(mask) => {
+                let mut message = Vec::new();
+                for (privacy, text) in texts {
+                    match privacy {
+                        Privacy::Public => message.extend_from_slice(text.to_bytes()),
+                        Privacy::Private => {
+                            message.extend_from_slice(mask.mask(text.to_bytes()).as_bytes())
+                        }
+                    }
+                }
+                masked = [(Privacy::Public, CString::new(message).unwrap())];
+                &masked[..]
+            }
+            None => texts,
+        };
+
+        let has_newlines = |texts: &[(Privacy, CString)]| {
+            texts.iter().any(|(_, text)| {
+                text.to_bytes().contains(&b'\n') || text.to_bytes().contains(&b'\r')
+            })
+        };
+        let escaped: Vec<_>;
+        let texts = if newlines == Newlines::Escape && has_newlines(texts) {
+            escaped = texts
+                .iter()
+                .map(|(privacy, text)| {
+                    let text = chunk::escape_newlines(&text.to_string_lossy());
+                    (*privacy, CString::new(text).unwrap())
+                })
+                .collect();
+            &escaped[..]
+        } else {
+            texts
+        };
+
+        let split = newlines == Newlines::Split && has_newlines(texts);
+        let len: usize = texts.iter().map(|(_, text)| text.to_bytes().len()).sum();
+        if split || self.chunk_size.is_some_and(|max_len| len > max_len) {
+            return self.log_text_pieces(level, texts, split);
+        }
+        self.emit_texts(level, texts);
+    }
+
+    /// Like `log_pieces`, for messages made of public and private texts, which
+    /// are split into lines first if `split_lines` is set. Each piece keeps the
+    /// privacy of the texts it was cut from.
+    #[cold]
+    fn log_text_pieces(&self, level: Level, texts: &[(Privacy, CString)], split_lines: bool) {
+        let texts: Vec<_> = texts
+            .iter()
+            .map(|(privacy, text)| (*privacy, text.to_string_lossy()))
+            .collect();
+        let message: String = texts.iter().map(|(_, text)| &**text).collect();
+        let max_len = self.chunk_size.unwrap_or(usize::MAX);
+        let pieces = if split_lines {
+            chunk::pieces(chunk::lines(&message), max_len)
+        } else {
+            chunk::pieces([message.as_str()], max_len)
+        };
+        if pieces.is_empty() {
+            return self.emit_texts(level, &[(Privacy::Public, CString::default())]);
+        }
+        let id = chunk::next_id();
+
+        for (i, piece) in pieces.iter().enumerate() {
+            // Pieces are slices of `message`, but line endings are left out.
+            let start = piece.as_ptr() as usize - message.as_ptr() as usize;
+            let end = start + piece.len();
+            let mut chunk = Vec::new();
+            if pieces.len() > 1 {
+                let prefix = format!("[{}/{}] [{:08x}] ", i + 1, pieces.len(), id);
+                chunk.push((Privacy::Public, prefix));
+            }
+
+            let mut offset = 0;
+            for (privacy, text) in &texts {
+                let (from, to) = (start.max(offset), end.min(offset + text.len()));
+                if from < to {
+                    let text = &text[from - offset..to - offset];
+                    match chunk.last_mut() {
+                        Some((last, joined)) if last == privacy => joined.push_str(text),
+                        _ => chunk.push((*privacy, text.to_owned())),
+                    }
+                }
+                offset += text.len();
+            }
+
+            let chunk: Vec<_> = chunk
+                .into_iter()
+                .map(|(privacy, text)| (privacy, CString::new(text).unwrap()))
+                .collect();
+            self.emit_texts(level, &chunk);
+        }
+    }
+
+    fn emit_texts(&self, level: Level, texts: &[(Privacy, CString)]) {
+        if let [(Privacy::Public, text)] = texts {
+            return self.inner.log(level, text);
+        }
+
+        let parts: Vec<_> = texts
+            .iter()
+            .map(|(privacy, text)| Part {
+                privacy: *privacy,
+                text,
+            })
+            .collect();
+
+        self.inner.log_parts(level, &parts);
+    }
+
+    /// Logs a message whose format was checked at compile time. Usually
+    /// called through `os_log_static!`. The arguments' privacy is part of the
+    /// format, so the log's hash mask and default privacy don't apply.
+    #[inline]
+    pub fn with_static_format(
+        &self,
+        level: Level,
+        format: &StaticFormat,
+        args: &[(Privacy, Argument<'_>)],
+    ) {
+        self.inner.log_static(level, format, args);
     }
 
     #[inline]
     pub fn debug(&self, message: &str) {
-        let message = to_cstr(message);
-        unsafe { wrapped_os_log_debug(self.inner, message.as_ptr()) }
+        self.with_level(Level::Debug, message);
     }
 
     #[inline]
     pub fn info(&self, message: &str) {
-        let message = to_cstr(message);
-        unsafe { wrapped_os_log_info(self.inner, message.as_ptr()) }
+        self.with_level(Level::Info, message);
     }
 
     #[inline]
     pub fn default(&self, message: &str) {
-        let message = to_cstr(message);
-        unsafe { wrapped_os_log_default(self.inner, message.as_ptr()) }
+        self.with_level(Level::Default, message);
     }
 
     #[inline]
     pub fn error(&self, message: &str) {
-        let message = to_cstr(message);
-        unsafe { wrapped_os_log_error(self.inner, message.as_ptr()) }
+        self.with_level(Level::Error, message);
     }
 
     #[inline]
     pub fn fault(&self, message: &str) {
-        let message = to_cstr(message);
-        unsafe { wrapped_os_log_fault(self.inner, message.as_ptr()) }
+        self.with_level(Level::Fault, message);
+    }
+
+    #[inline]
+    pub fn debug_cstr(&self, message: &CStr) {
+        self.with_level_cstr(Level::Debug, message);
+    }
+
+    #[inline]
+    pub fn info_cstr(&self, message: &CStr) {
+        self.with_level_cstr(Level::Info, message);
+    }
+
+    #[inline]
+    pub fn default_cstr(&self, message: &CStr) {
+        self.with_level_cstr(Level::Default, message);
+    }
+
+    #[inline]
+    pub fn error_cstr(&self, message: &CStr) {
+        self.with_level_cstr(Level::Error, message);
+    }
+
+    #[inline]
+    pub fn fault_cstr(&self, message: &CStr) {
+        self.with_level_cstr(Level::Fault, message);
     }
 
     #[inline]
     pub fn level_is_enabled(&self, level: Level) -> bool {
-        unsafe { os_log_type_enabled(self.inner, level as u8) }
+        self.inner.level_is_enabled(level)
     }
 }
 
@@ -128,6 +819,18 @@ impl OsLog {
 mod tests {
     use super::*;
 
+    struct UnsupportedBackend;
+
+    impl Backend for UnsupportedBackend {
+        fn create(&self, _: &CStr, _: &CStr) -> Result<Box<dyn Handle>, Error> {
+            Err(Error::UnsupportedPlatform)
+        }
+
+        fn global(&self) -> Result<Box<dyn Handle>, Error> {
+            Err(Error::UnsupportedPlatform)
+        }
+    }
+
     #[test]
     fn test_subsystem_interior_null() {
         let log = OsLog::new("com.example.oslog\0test", "category");
@@ -140,18 +843,618 @@ mod tests {
         log.with_level(Level::Debug, "Hi");
     }
 
+    #[test]
+    fn test_try_new_interior_null() {
+        assert_eq!(
+            OsLog::try_new("com.example\0oslog", "category").err(),
+            Some(Error::InvalidSubsystem(11))
+        );
+        assert_eq!(
+            OsLog::try_new("com.example.oslog", "cat\0egory").err(),
+            Some(Error::InvalidCategory(3))
+        );
+        assert!(OsLog::try_new("com.example.oslog", "category").is_ok());
+        assert!(OsLog::try_global().is_ok());
+    }
+
+    #[test]
+    fn test_try_with_backend_error() {
+        assert_eq!(
+            OsLog::try_with_backend(&UnsupportedBackend, "com.example.oslog", "category").err(),
+            Some(Error::UnsupportedPlatform)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Failed to create log")]
+    fn test_with_backend_error_panics() {
+        OsLog::with_backend(&UnsupportedBackend, "com.example.oslog", "category");
+    }
+
+    #[test]
+    fn test_clone() {
+        let capture = testing::CaptureBackend::new();
+        let log = capture.log("com.example.oslog", "category");
+        let clone = log.clone();
+        drop(log);
+        clone.info("From the clone");
+
+        assert_eq!(capture.for_category("category").len(), 1);
+        OsLog::global().clone().info("Global");
+        OsLog::disabled().clone().info("Disabled");
+    }
+
+    #[test]
+    fn test_shared_log() {
+        let log = OsLog::shared("com.example.oslog", "shared");
+        log.info("Info");
+        OsLog::try_shared("com.example.oslog", "shared")
+            .unwrap()
+            .info("Info");
+        assert_eq!(
+            OsLog::try_shared("com.example.oslog", "sha\0red").err(),
+            Some(Error::InvalidCategory(3))
+        );
+    }
+
+    #[test]
+    fn test_split_privacy() {
+        use Privacy::*;
+
+        assert_eq!(
+            split_privacy(
+                "user {} logged in from {}{} {{ok}}",
+                &[(Private, &"alice"), (Public, &"10.0.0.1"), (Public, &":80")]
+            ),
+            vec![
+                (Public, "user ".into()),
+                (Private, "alice".into()),
+                (Public, " logged in from 10.0.0.1:80 {ok}".into()),
+            ]
+        );
+        assert_eq!(
+            split_privacy("{}{}", &[(Private, &1), (Private, &2)]),
+            vec![(Private, "12".into())]
+        );
+        assert_eq!(
+            split_privacy("missing {} and {", &[]),
+            vec![(Public, "missing {} and {".into())]
+        );
+    }
+
+    #[test]
+    fn test_os_log_format_check() {
+        __private::check_os_log_format("user {} from {}{{ok}}");
+        __private::check_os_log_format("");
+
+        for format in ["{:x}", "{0}", "{name}", "{:?}", "{:>8}", "trailing {"] {
+            let result = std::panic::catch_unwind(|| __private::check_os_log_format(format));
+            assert!(result.is_err(), "{:?} was accepted", format);
+        }
+    }
+
+    #[test]
+    fn test_os_log_macro() {
+        let capture = testing::CaptureBackend::new();
+        let log = capture.log("com.example.oslog", "Auth");
+        let name = String::from("alice");
+
+        os_log!(
+            log,
+            Level::Info,
+            "user {} logged in from {}",
+            private(name),
+            public("10.0.0.1")
+        );
+        os_log!(log, Level::Error, "unmarked {} is private", 42,);
+        os_log!(log, Level::Debug, "no arguments");
+        os_log!(OsLog::disabled(), Level::Fault, "{}", public(1));
+
+        capture.assert_logged(Level::Info, "user <private> logged in from 10.0.0.1");
+        capture.assert_logged(Level::Error, "unmarked <private> is private");
+        capture.assert_logged(Level::Debug, "no arguments");
+        capture.assert_count(3);
+    }
+
+    #[test]
+    fn test_log_fmt() {
+        let capture = testing::CaptureBackend::new();
+        let log = capture.log("com.example.oslog", "category");
+
+        log.log_fmt(Level::Info, format_args!("{} + {} = {}", 1, 2, 3));
+        log.log_fmt(Level::Debug, format_args!("static"));
+        log.log_fmt(Level::Error, format_args!("{}\0{}", "a", "\0b"));
+
+        capture.assert_logged(Level::Info, "1 + 2 = 3");
+        capture.assert_logged(Level::Debug, "static");
+        capture.assert_logged(Level::Error, "a(null)(null)b");
+    }
+
+    #[test]
+    fn test_log_fmt_skips_disabled_levels() {
+        struct Unformattable;
+
+        impl fmt::Display for Unformattable {
+            fn fmt(&self, _: &mut fmt::Formatter<'_>) -> fmt::Result {
+                panic!("formatted a disabled message");
+            }
+        }
+
+        OsLog::disabled().log_fmt(Level::Fault, format_args!("{}", Unformattable));
+    }
+
+    #[test]
+    fn test_fmt_macros() {
+        let capture = testing::CaptureBackend::new();
+        let log = capture.log("com.example.oslog", "category");
+        let count = 3;
+
+        os_log_fmt!(log, Level::Info, "{count} items");
+        os_log_debug!(log, "Debug {}", 1);
+        os_log_info!(log, "Info {:?}", "quoted");
+        os_log_default!(log, "Default");
+        os_log_error!(&log, "Error {}", 4,);
+        os_log_fault!(log, "Fault {:#x}", 255);
+
+        capture.assert_logged(Level::Info, "3 items");
+        capture.assert_logged(Level::Debug, "Debug 1");
+        capture.assert_logged(Level::Info, "Info \"quoted\"");
+        capture.assert_logged(Level::Default, "Default");
+        capture.assert_logged(Level::Error, "Error 4");
+        capture.assert_logged(Level::Fault, "Fault 0xff");
+    }
+
+    #[test]
+    fn test_short_messages_dont_allocate() {
+        let log = testing::NullBackend.log("com.example.test", "Null");
+        let long = "a".repeat(buffer::STACK_CAPACITY);
+
+        let count = buffer::tests::allocations(|| {
+            log.with_level(Level::Info, "Hello\0there");
+            log.debug("Debug");
+            log.log_fmt(Level::Error, format_args!("{} {}", 1, "two"));
+            os_log_fault!(log, "{:?}", [1, 2, 3]);
+        });
+        assert_eq!(count, 0);
+
+        let count = buffer::tests::allocations(|| log.fault(&long));
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_cstr_messages() {
+        let capture = testing::CaptureBackend::new();
+        let log = OsLog::with_backend(&capture, c"com.example.test", c"Settings");
+        log.with_level_cstr(Level::Info, c"Hello");
+        log.debug_cstr(c"One");
+        log.default_cstr(&CString::new("Two").unwrap());
+        log.error_cstr(c"Three");
+        log.fault_cstr(c"\u{1F601}");
+
+        let entries = capture.entries();
+        assert_eq!(entries.len(), 5);
+        assert_eq!(entries[0].subsystem, "com.example.test");
+        assert_eq!(entries[0].category, "Settings");
+        capture.assert_logged(Level::Info, "Hello");
+        capture.assert_logged(Level::Debug, "One");
+        capture.assert_logged(Level::Default, "Two");
+        capture.assert_logged(Level::Error, "Three");
+        capture.assert_logged(Level::Fault, "\u{1F601}");
+
+        let log = testing::NullBackend.log("com.example.test", "Null");
+        let count = buffer::tests::allocations(|| log.info_cstr(c"Hello"));
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn test_new_accepts_strings_and_cstrs() {
+        let capture = testing::CaptureBackend::new();
+        let subsystem = String::from("com.example.test");
+        OsLog::with_backend(&capture, &subsystem, c"One").info("a");
+        OsLog::with_backend(&capture, c"com.example.test", "Two\0").info("b");
+        OsLog::with_backend(&capture, subsystem.as_str(), CString::new("Three").unwrap()).info("c");
+
+        let categories: Vec<_> = capture.entries().into_iter().map(|e| e.category).collect();
+        assert_eq!(categories, ["One", "Two(null)", "Three"]);
+    }
+
+    #[test]
+    fn test_chunking() {
+        let capture = testing::CaptureBackend::new();
+        // Leaves 4 bytes after the `[i/n] [id] ` prefix.
+        let log = capture
+            .log("com.example.test", "Settings")
+            .with_chunk_size(Some(21));
+
+        log.info("abcdéfg\u{1F601}abcdéfg\u{1F601}");
+        log.info(&"a".repeat(21));
+        log.log_fmt(
+            Level::Info,
+            format_args!("{}{}", 1234567890, 1234567890123u64),
+        );
+
+        let messages = capture.messages();
+        assert_eq!(messages.len(), 13);
+        assert!(messages.iter().all(|message| message.len() <= 21));
+
+        let id = &messages[0][6..16];
+        assert_eq!(messages[0], format!("[1/6] {} abcd", id));
+        assert_eq!(messages[1], format!("[2/6] {} éfg", id));
+        assert_eq!(messages[2], format!("[3/6] {} \u{1F601}", id));
+        assert_eq!(messages[6], "a".repeat(21));
+
+        let other = &messages[7][6..16];
+        assert_ne!(id, other);
+        assert_eq!(messages[7], format!("[1/6] {} 1234", other));
+        assert_eq!(messages[12], format!("[6/6] {} 123", other));
+    }
+
+    #[test]
+    fn test_chunking_measures_sanitised_messages() {
+        let capture = testing::CaptureBackend::new();
+        let log = capture
+            .log("com.example.test", "Settings")
+            .with_chunk_size(Some(21))
+            .with_control_chars(ControlChars::Escape);
+
+        // 4 bytes, but 24 once the nul bytes are replaced.
+        log.info("\0\0\0\0");
+        // 6 bytes, but 24 once the escapes are written out.
+        log.info("\x1b\x1b\x1b\x1b\x1b\x1b");
+
+        let messages = chunk::unprefixed(capture.messages());
+        assert_eq!(messages.concat(), "(null)".repeat(4) + &"\\x1b".repeat(6));
+        assert_eq!(messages.len(), 12);
+    }
+
+    #[test]
+    fn test_chunking_marked_messages() {
+        let capture = testing::CaptureBackend::new();
+        let log = capture
+            .log("com.example.test", "Settings")
+            .with_chunk_size(Some(21));
+
+        log.log_fmt(
+            Level::Info,
+            format_args!("{}klmnopqrstuv", Private("abcdefghij")),
+        );
+        let masked = log
+            .clone()
+            .with_hash_mask(Some(HashMask::with_salt(b"salt")));
+        masked.log_fmt(Level::Info, format_args!("{}", Private("abcdefghij")));
+
+        let messages = chunk::unprefixed(capture.messages());
+        assert_eq!(
+            messages[..6],
+            [
+                "<private>",
+                "<private>",
+                "<private>kl",
+                "mnop",
+                "qrst",
+                "uv"
+            ]
+        );
+        let hash = HashMask::with_salt(b"salt").mask(b"abcdefghij");
+        assert_eq!(messages[6..].concat(), hash);
+    }
+
+    #[test]
+    fn test_newlines() {
+        let capture = testing::CaptureBackend::new();
+        let log = capture.log("com.example.test", "Settings");
+        let message = "One\nTwo\r\n\nThree\n";
+
+        log.with_newlines(Level::Info, message, Newlines::Keep);
+        capture.assert_logged(Level::Info, message);
+        capture.clear();
+
+        log.with_newlines(Level::Info, message, Newlines::Escape);
+        capture.assert_logged(Level::Info, "One\\nTwo\\r\\n\\nThree\\n");
+        capture.clear();
+
+        log.with_newlines(Level::Info, message, Newlines::Split);
+        let messages = capture.messages();
+        let id = &messages[0][6..16];
+        assert_eq!(
+            messages,
+            [
+                format!("[1/3] {} One", id),
+                format!("[2/3] {} Two", id),
+                format!("[3/3] {} Three", id),
+            ]
+        );
+        capture.clear();
+
+        log.with_newlines(Level::Info, "a\rb", Newlines::Split);
+        let messages = chunk::unprefixed(capture.messages());
+        assert_eq!(messages, ["a", "b"]);
+        capture.clear();
+
+        log.with_newlines(Level::Info, "Single line", Newlines::Split);
+        log.with_newlines(Level::Info, "Trailing newline\r\n", Newlines::Split);
+        capture.assert_logged(Level::Info, "Single line");
+        capture.assert_logged(Level::Info, "Trailing newline");
+        capture.assert_count(2);
+        capture.clear();
+
+        log.with_newlines(Level::Info, "\n", Newlines::Split);
+        log.with_newlines(Level::Info, "\r\n\r\n", Newlines::Split);
+        log.log_fmt_with_newlines(
+            Level::Info,
+            format_args!("{}", Public("\n")),
+            Newlines::Split,
+        );
+        let entries = capture.take();
+        assert_eq!(entries.len(), 3);
+        assert!(entries.iter().all(|e| e.message.is_empty()));
+    }
+
+    #[test]
+    fn test_newlines_with_chunking() {
+        let capture = testing::CaptureBackend::new();
+        let log = capture
+            .log("com.example.test", "Settings")
+            .with_chunk_size(Some(21));
+
+        log.with_newlines(Level::Info, "abcdef\ngh", Newlines::Split);
+        log.with_newlines(Level::Info, "abcdefghijkl\nmnopqrst", Newlines::Escape);
+
+        let messages = chunk::unprefixed(capture.messages());
+        assert_eq!(
+            messages,
+            ["abcd", "ef", "gh", "abcd", "efgh", "ijkl", "\\nmn", "opqr", "st"]
+        );
+    }
+
+    #[test]
+    fn test_chunking_is_off_by_default() {
+        let capture = testing::CaptureBackend::new();
+        let message = "a".repeat(4096);
+        capture.log("com.example.test", "Settings").info(&message);
+        capture.assert_logged(Level::Info, &message);
+    }
+
+    #[test]
+    fn test_nul_policy() {
+        let capture = testing::CaptureBackend::new();
+        let log = OsLog::try_with_nul_policy(
+            &capture,
+            "com.example\0test",
+            "Settings\0",
+            NulPolicy::Escape,
+        )
+        .unwrap();
+        log.info("a\0b");
+        log.log_fmt(Level::Info, format_args!("{}\0", 1));
+        os_log!(log, Level::Info, "{}\0{}", public("c\0"), "d");
+
+        let entry = &capture.entries()[0];
+        assert_eq!(entry.subsystem, "com.example\\0test");
+        assert_eq!(entry.category, "Settings\\0");
+        capture.assert_logged(Level::Info, "a\\0b");
+        capture.assert_logged(Level::Info, "1\\0");
+        capture.assert_logged(Level::Info, "c\\0\\0<private>");
+    }
+
+    #[test]
+    fn test_nul_policy_truncate() {
+        let capture = testing::CaptureBackend::new();
+        let log =
+            OsLog::try_with_nul_policy(&capture, "a\0b", "c\0d", NulPolicy::Truncate).unwrap();
+        log.with_newlines(Level::Info, "One\0\nTwo", Newlines::Split);
+        os_log!(log, Level::Info, "{} {}", public("e\0f"), public("g"));
+
+        let entries = capture.entries();
+        assert_eq!((&*entries[0].subsystem, &*entries[0].category), ("a", "c"));
+        assert_eq!(entries[0].message_str(), "One");
+        assert_eq!(entries[1].message_str(), "e");
+    }
+
+    #[test]
+    fn test_nul_policy_reject() {
+        let capture = testing::CaptureBackend::new();
+        assert_eq!(
+            OsLog::try_with_nul_policy(&capture, "a\0", "b", NulPolicy::Reject).err(),
+            Some(Error::InvalidSubsystem(1))
+        );
+        assert_eq!(
+            OsLog::try_with_nul_policy(&capture, "a", "bc\0", NulPolicy::Reject).err(),
+            Some(Error::InvalidCategory(2))
+        );
+
+        let log = OsLog::try_with_nul_policy(&capture, "a", "b", NulPolicy::Reject).unwrap();
+        assert_eq!(
+            log.try_with_level(Level::Info, "One\0"),
+            Err(Error::InvalidMessage(3))
+        );
+        log.info("Two\0");
+        log.log_fmt(Level::Info, format_args!("{}\0", "Three"));
+        os_log!(log, Level::Info, "{}", public("Four\0"));
+        assert_eq!(log.try_with_level(Level::Info, "Five"), Ok(()));
+
+        assert_eq!(capture.entries().len(), 1);
+        capture.assert_logged(Level::Info, "Five");
+    }
+
+    #[test]
+    fn test_disabled_log() {
+        let log = OsLog::disabled();
+        assert!(!log.level_is_enabled(Level::Debug));
+        assert!(!log.level_is_enabled(Level::Fault));
+        log.with_level(Level::Fault, "Fault");
+        log.debug("Debug");
+    }
+
     #[test]
     fn test_message_interior_null() {
         let log = OsLog::new("com.example.oslog", "category");
         log.with_level(Level::Debug, "Hi\0test");
     }
 
+    #[test]
+    fn test_message_interior_null_is_replaced() {
+        let capture = testing::CaptureBackend::new();
+        capture
+            .log("com.example.oslog", "category")
+            .debug("Hi\0test");
+        capture.assert_logged(Level::Debug, "Hi(null)test");
+    }
+
     #[test]
     fn test_message_emoji() {
         let log = OsLog::new("com.example.oslog", "category");
         log.with_level(Level::Debug, "\u{1F601}");
     }
 
+    #[test]
+    fn test_control_chars() {
+        let capture = testing::CaptureBackend::new();
+        let log = capture
+            .log("com.example.test", "Settings")
+            .with_control_chars(ControlChars::Strip);
+        log.info("\x1b[31m\u{1F601}\x1b[0m\r");
+        log.log_fmt(Level::Info, format_args!("\x1b]0;{}\x07{}", "title", "Two"));
+        os_log!(log, Level::Info, "\x1b[1m{}", public("\x1b[0mThree"));
+        capture.assert_logged(Level::Info, "\u{1F601}");
+        capture.assert_logged(Level::Info, "Two");
+        capture.assert_logged(Level::Info, "Three");
+
+        let log = log.with_control_chars(ControlChars::Escape);
+        log.info("\x07\u{1F601}");
+        capture.assert_logged(Level::Info, "\\x07\u{1F601}");
+    }
+
+    #[test]
+    fn test_private_and_public_wrappers() {
+        let capture = testing::CaptureBackend::new();
+        let log = capture.log("com.example.test", "Settings");
+
+        log.log_fmt(
+            Level::Info,
+            format_args!("{} signed in from {}", Private("alice"), Public("1.2.3.4")),
+        );
+        os_log_error!(log, "token {:?}", Private("abc"));
+        log.info(&format!("{}", Private("leaked?")));
+
+        capture.assert_logged(Level::Info, "<private> signed in from 1.2.3.4");
+        capture.assert_logged(Level::Error, "token <private>");
+        capture.assert_logged(Level::Info, "<private>");
+        assert!(capture.entries().iter().all(|e| !e
+            .message_str()
+            .contains(['\u{FDD0}', '\u{FDD1}', '\u{FDD2}'])));
+    }
+
+    #[test]
+    fn test_private_values_survive_stripping() {
+        let capture = testing::CaptureBackend::new();
+        let log = capture
+            .log("com.example.test", "Settings")
+            .with_control_chars(ControlChars::Strip);
+
+        log.log_fmt(
+            Level::Info,
+            format_args!("user {}{} done", "\x1b]", Private("\x07secret-token")),
+        );
+        log.log_fmt(
+            Level::Info,
+            format_args!("user {}{} done", "\u{9d}", Private("\u{9c}secret-token")),
+        );
+
+        capture.assert_not_logged("user secret-token done");
+        capture.assert_logged(Level::Info, "user <private> done");
+        capture.assert_count(2);
+    }
+
+    #[test]
+    fn test_newlines_with_private_values() {
+        let capture = testing::CaptureBackend::new();
+        let log = capture.log("com.example.test", "Settings");
+        let log_with = |value, newlines| {
+            let args = format_args!("{} line1\nforged line", Private(value));
+            log.log_fmt_with_newlines(Level::Info, args, newlines);
+        };
+
+        log_with("x", Newlines::Escape);
+        capture.assert_logged(Level::Info, "<private> line1\\nforged line");
+        capture.assert_count(1);
+        capture.clear();
+
+        log_with("x\ny", Newlines::Split);
+        let messages = chunk::unprefixed(capture.messages());
+        assert_eq!(messages, ["<private>", "<private> line1", "forged line"]);
+        capture.clear();
+
+        log.log_fmt_with_newlines(
+            Level::Info,
+            format_args!("{}", Private("x")),
+            Newlines::Split,
+        );
+        capture.assert_logged(Level::Info, "<private>");
+        capture.assert_count(1);
+    }
+
+    #[test]
+    fn test_default_privacy() {
+        let capture = testing::CaptureBackend::new();
+        let log = capture
+            .log("com.example.test", "Auth")
+            .with_default_privacy(Privacy::Private);
+
+        log.info("token abc");
+        log.info_cstr(c"token def");
+        log.log_fmt(
+            Level::Info,
+            format_args!("{} from {}", "bob", Public("1.2.3.4")),
+        );
+        os_log!(log, Level::Info, "{} from {}", "bob", public("1.2.3.4"));
+
+        let messages = capture.messages();
+        assert_eq!(
+            messages,
+            [
+                "<private>",
+                "<private>",
+                "<private>1.2.3.4",
+                "<private> from 1.2.3.4"
+            ]
+        );
+
+        // Text can't end the private run early by including a marker.
+        log.log_fmt(
+            Level::Info,
+            format_args!("{} {}", "\u{FDD1}card 4111-1111", Public(1)),
+        );
+        capture.assert_logged(Level::Info, "<private>1");
+
+        let mask = HashMask::with_salt(b"salt");
+        let masked = log.clone().with_hash_mask(Some(mask));
+        masked.info("token abc");
+        capture.assert_logged(Level::Info, &mask.mask(b"token abc"));
+    }
+
+    #[test]
+    fn test_hash_mask() {
+        let capture = testing::CaptureBackend::new();
+        let mask = HashMask::with_salt(b"salt");
+        let log = capture
+            .log("com.example.test", "Settings")
+            .with_hash_mask(Some(mask));
+
+        os_log!(
+            log,
+            Level::Info,
+            "{} signed in from {}",
+            "alice",
+            public("1.2.3.4")
+        );
+        log.log_fmt(Level::Info, format_args!("{} signed out", Private("alice")));
+
+        let hash = mask.mask(b"alice");
+        capture.assert_logged(Level::Info, &format!("{} signed in from 1.2.3.4", hash));
+        capture.assert_logged(Level::Info, &format!("{} signed out", hash));
+    }
+
     #[test]
     fn test_global_log_with_level() {
         let log = OsLog::global();
@@ -1,14 +1,53 @@
-use crate::OsLog;
+use crate::backend::{self, Backend, ColorChoice, StderrBackend};
+#[cfg(feature = "redaction")]
+use crate::redact::{RedactionPreset, Redactor};
+use crate::template::Template;
+use crate::{ControlChars, Error, HashMask, Newlines, NulPolicy, OsLog, Privacy};
 use dashmap::DashMap;
 use log::{LevelFilter, Log, Metadata, Record};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Settings for a single category, applied when its log is created.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Category {
+    level: Option<LevelFilter>,
+    privacy: Option<Privacy>,
+    disabled: bool,
+}
+
+impl Category {
+    fn level(&self) -> Option<LevelFilter> {
+        if self.disabled {
+            Some(LevelFilter::Off)
+        } else {
+            self.level
+        }
+    }
+}
 
 #[derive(Default)]
 pub struct Config {
     pub(crate) subsystem: String,
     pub(crate) log_level: Option<LevelFilter>,
-    pub(crate) loggers: DashMap<String, (Option<LevelFilter>, OsLog)>,
+    pub(crate) categories: HashMap<String, Category>,
+    /// Logs are created when a category is first logged to, so they're built
+    /// from the finished config.
+    pub(crate) loggers: DashMap<String, OsLog>,
+    pub(crate) backend: Option<Arc<dyn Backend>>,
+    pub(crate) stderr: StderrBackend,
+    pub(crate) use_stderr: bool,
+    pub(crate) chunk_size: Option<usize>,
+    pub(crate) newlines: Newlines,
+    pub(crate) nul_policy: NulPolicy,
+    pub(crate) template: Option<Template>,
+    pub(crate) control_chars: ControlChars,
+    pub(crate) hash_mask: Option<HashMask>,
+    #[cfg(feature = "redaction")]
+    pub(crate) redactor: Redactor,
 }
 
+#[allow(clippy::needless_return)]
 impl Config {
     pub fn with_subsystem(mut self, subsystem: String) -> Self {
         self.subsystem = subsystem;
@@ -21,15 +60,211 @@ impl Config {
         return self;
     }
 
-    /// Sets or updates the category's level filter.
-    pub fn with_category_level_filter(self, category: &str, level: LevelFilter) -> Self {
-        self.loggers
-            .entry(category.into())
-            .and_modify(|(existing_level, _)| *existing_level = Some(level))
-            .or_insert((Some(level), OsLog::new(&self.subsystem, category)));
+    /// Writes through `backend` instead of the platform's default.
+    pub fn with_backend(mut self, backend: Arc<dyn Backend>) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    /// Sets the line format used when logging to stderr, either because the
+    /// target has no unified logging or because a `StderrBackend` was
+    /// requested with `with_stderr`. See `StderrBackend` for the placeholders.
+    pub fn with_stderr_format(mut self, format: &str) -> Self {
+        self.stderr = self.stderr.with_format(format);
+        self
+    }
+
+    /// Sets whether lines logged to stderr are coloured. Defaults to
+    /// `ColorChoice::Auto`.
+    pub fn with_stderr_color(mut self, choice: ColorChoice) -> Self {
+        self.stderr = self.stderr.with_color(choice);
+        self
+    }
+
+    /// Logs to stderr even on targets with unified logging, e.g. while
+    /// running from a terminal during development. A backend set with
+    /// `with_backend` takes precedence.
+    pub fn with_stderr(mut self) -> Self {
+        self.use_stderr = true;
+        self
+    }
+
+    /// Splits messages longer than `chunk_size` bytes into numbered entries,
+    /// see `OsLog::with_chunk_size`.
+    pub fn with_chunk_size(mut self, chunk_size: Option<usize>) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// Sets how newlines in messages are handled. Defaults to
+    /// `Newlines::Keep`.
+    pub fn with_newlines(mut self, newlines: Newlines) -> Self {
+        self.newlines = newlines;
+        self
+    }
+
+    /// Sets how nul bytes in the subsystem, categories and messages are
+    /// handled. Records are dropped if it rejects them, and so are all
+    /// records for a rejected subsystem or category.
+    pub fn with_nul_policy(mut self, policy: NulPolicy) -> Self {
+        self.nul_policy = policy;
+        self
+    }
+
+    /// Logs each record's message through `template`, e.g.
+    /// `"[{module}:{line}] {thread} {message}"`. The placeholders are
+    /// `{module}`, `{file}`, `{line}` and `{target}` from the record,
+    /// `{thread}` and `{thread_id}` for the logging thread, and `{message}`.
+    /// `{{` and `}}` produce literal braces, and missing values render as `-`.
+    pub fn with_template(mut self, template: &str) -> Self {
+        self.template = Some(Template::parse(template));
+        self
+    }
 
+    /// Sets how control characters and ANSI escape sequences in messages are
+    /// handled, e.g. to stop them garbling terminals or forging lines in
+    /// text logs. This covers every record logged through `OsLogger`, but
+    /// not text logged directly with `OsLog`'s `_cstr` methods.
+    pub fn with_control_chars(mut self, control_chars: ControlChars) -> Self {
+        self.control_chars = control_chars;
+        self
+    }
+
+    /// Replaces private values with hashes salted by `hash_mask`, e.g.
+    /// `HashMask::per_process()` or `HashMask::per_install(path)`.
+    pub fn with_hash_mask(mut self, hash_mask: Option<HashMask>) -> Self {
+        self.hash_mask = hash_mask;
+        self
+    }
+
+    /// Replaces matches of the regular expression `pattern` in each message
+    /// with `replacement`, which may refer to capture groups as `$1` or
+    /// `$name`. Rules are applied in the order they're added, after the
+    /// template and `with_control_chars`. In messages with `Private` or `Public` values, each value and
+    /// the text between them are matched separately.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pattern` isn't a valid regular expression.
+    #[cfg(feature = "redaction")]
+    pub fn with_redaction_rule(mut self, pattern: &str, replacement: &str) -> Self {
+        self.redactor.add_rule(pattern, replacement);
+        self
+    }
+
+    /// Adds one of the built-in redaction rules.
+    #[cfg(feature = "redaction")]
+    pub fn with_redaction_preset(mut self, preset: RedactionPreset) -> Self {
+        self.redactor.add_preset(preset);
+        self
+    }
+
+    /// Sets or updates the category's level filter.
+    pub fn with_category_level_filter(mut self, category: &str, level: LevelFilter) -> Self {
+        self.category(category).level = Some(level);
         return self;
     }
+
+    /// Sets whether messages logged to the category are public or private.
+    /// `Public` and `Private` values within them keep their own privacy.
+    /// Messages are public by default.
+    pub fn with_category_privacy(mut self, category: &str, privacy: Privacy) -> Self {
+        self.category(category).privacy = Some(privacy);
+        self
+    }
+
+    /// Discards everything logged to the category, at next to no cost. Level
+    /// filters set for it later don't re-enable it.
+    pub fn with_disabled_category(mut self, category: &str) -> Self {
+        self.category(category).disabled = true;
+        self
+    }
+
+    fn category(&mut self, category: &str) -> &mut Category {
+        self.categories.entry(category.into()).or_default()
+    }
+
+    /// Logs using the platform's backend come from the registry, so they're
+    /// shared with any `OsLog::shared` callers. The registry's logs use the
+    /// default `NulPolicy`, so others are created separately.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the backend can't create the log.
+    pub(crate) fn create_log(&self, category: &str) -> OsLog {
+        let settings = self.categories.get(category).copied().unwrap_or_default();
+        if settings.disabled {
+            return OsLog::from_handle(self.backend().disabled());
+        }
+
+        let shared = cfg!(target_vendor = "apple")
+            && self.backend.is_none()
+            && !self.use_stderr
+            && self.nul_policy == NulPolicy::default();
+
+        let log = if shared {
+            OsLog::shared(&self.subsystem, category)
+        } else {
+            match OsLog::try_with_nul_policy(
+                self.backend(),
+                &self.subsystem,
+                category,
+                self.nul_policy,
+            ) {
+                Ok(log) => log,
+                Err(Error::InvalidSubsystem(_) | Error::InvalidCategory(_)) => {
+                    OsLog::from_handle(self.backend().disabled())
+                }
+                Err(err) => panic!("Failed to create log: {}", err),
+            }
+        };
+
+        log.with_chunk_size(self.chunk_size)
+            .with_nul_policy(self.nul_policy)
+            .with_control_chars(self.control_chars)
+            .with_hash_mask(self.hash_mask)
+            .with_default_privacy(settings.privacy.unwrap_or(Privacy::Public))
+    }
+
+    #[cfg(feature = "redaction")]
+    fn log_redacted(&self, log: &OsLog, level: crate::Level, record: &Record) {
+        use std::borrow::Cow;
+        use std::fmt::Write;
+
+        if !log.level_is_enabled(level) {
+            return;
+        }
+
+        let mut message = String::new();
+        let marked = crate::privacy::capture(log.hash_mask, || {
+            let _ = match &self.template {
+                Some(template) => write!(message, "{}", template.render(record)),
+                None => message.write_fmt(*record.args()),
+            };
+        });
+
+        // Control characters are handled first so escape sequences can't
+        // split a match, and rules are applied between the privacy markers so
+        // they can't match across or remove them.
+        let redact = |text: &str| {
+            let text = self.control_chars.apply(text);
+            self.redactor.apply(&text).into_owned()
+        };
+        let redacted = if marked {
+            crate::privacy::map_marked(&message, |text| Cow::Owned(redact(text)))
+        } else {
+            redact(&message)
+        };
+        log.log_preformatted(level, &redacted, marked, self.newlines);
+    }
+
+    fn backend(&self) -> &dyn Backend {
+        match &self.backend {
+            Some(backend) => backend.as_ref(),
+            None if cfg!(target_vendor = "apple") && !self.use_stderr => backend::platform(),
+            None => &self.stderr,
+        }
+    }
 }
 
 pub struct OsLogger {
@@ -56,9 +291,9 @@ impl Log for OsLogger {
     fn enabled(&self, metadata: &Metadata) -> bool {
         let max_level = self
             .config()
-            .loggers
+            .categories
             .get(metadata.target())
-            .and_then(|pair| pair.0)
+            .and_then(Category::level)
             .unwrap_or_else(log::max_level);
 
         metadata.level() <= max_level
@@ -66,14 +301,34 @@ impl Log for OsLogger {
 
     fn log(&self, record: &Record) {
         if self.enabled(record.metadata()) {
-            let pair = self
-                .config()
-                .loggers
-                .entry(record.target().into())
-                .or_insert((None, OsLog::new(&self.config().subsystem, record.target())));
-
-            let message = std::format!("{}", record.args());
-            pair.1.with_level(record.level().into(), &message);
+            let config = self.config();
+            let level = record.level().into();
+            let write = |log: &OsLog| {
+                #[cfg(feature = "redaction")]
+                if !config.redactor.is_empty() {
+                    return config.log_redacted(log, level, record);
+                }
+
+                match &config.template {
+                    Some(template) => log.log_fmt_with_newlines(
+                        level,
+                        format_args!("{}", template.render(record)),
+                        config.newlines,
+                    ),
+                    None => log.log_fmt_with_newlines(level, *record.args(), config.newlines),
+                }
+            };
+
+            // Look up existing categories first, as `entry` needs an owned key.
+            match config.loggers.get(record.target()) {
+                Some(log) => write(&log),
+                None => write(
+                    &config
+                        .loggers
+                        .entry(record.target().into())
+                        .or_insert_with(|| config.create_log(record.target())),
+                ),
+            }
         }
     }
 
@@ -93,8 +348,25 @@ pub fn init_once(config: Config) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::{CaptureBackend, NullBackend};
+    use crate::Level;
     use log::{debug, error, info, trace, warn};
 
+    /// Loggers follow the `log` crate's max level, which `init_once` sets to
+    /// `Trace` in the other tests.
+    fn logger(config: Config) -> OsLogger {
+        log::set_max_level(LevelFilter::Trace);
+        OsLogger::new(config)
+    }
+
+    fn record<'a>(target: &'a str, level: log::Level, args: std::fmt::Arguments<'a>) -> Record<'a> {
+        Record::builder()
+            .target(target)
+            .level(level)
+            .args(args)
+            .build()
+    }
+
     #[test]
     fn test_basic_usage() {
         init_once(
@@ -160,4 +432,382 @@ mod tests {
         warn!(target: "Database", "Warn");
         error!("Error");
     }
+
+    #[test]
+    fn test_category_routing_and_filtering() {
+        let capture = CaptureBackend::new();
+        let logger = logger(
+            Config::default()
+                .with_subsystem(String::from("com.example.oslog"))
+                .with_backend(Arc::new(capture.clone()))
+                .with_category_level_filter("Settings", LevelFilter::Warn)
+                .with_category_level_filter("Database", LevelFilter::Info),
+        );
+
+        let log = |target: &str, level: log::Level, message: &str| {
+            logger.log(&record(target, level, format_args!("{}", message)));
+        };
+
+        log("Settings", log::Level::Info, "Filtered by category");
+        log("Settings", log::Level::Warn, "Settings warning");
+        log("Database", log::Level::Debug, "Also filtered by category");
+        log("Database", log::Level::Info, "Database info");
+
+        assert_eq!(
+            capture
+                .entries()
+                .iter()
+                .map(|e| (e.subsystem.as_str(), e.category.as_str(), e.level))
+                .collect::<Vec<_>>(),
+            vec![
+                ("com.example.oslog", "Settings", Level::Error),
+                ("com.example.oslog", "Database", Level::Default),
+            ]
+        );
+        capture.assert_logged(Level::Error, "Settings warning");
+        capture.assert_logged(Level::Default, "Database info");
+    }
+
+    #[test]
+    fn test_settings_apply_regardless_of_order() {
+        let capture = CaptureBackend::new();
+        let logger = logger(
+            Config::default()
+                .with_category_level_filter("Settings", LevelFilter::Warn)
+                .with_category_privacy("Settings", Privacy::Private)
+                .with_disabled_category("Noise")
+                .with_subsystem(String::from("com.example.oslog"))
+                .with_backend(Arc::new(capture.clone()))
+                .with_control_chars(ControlChars::Strip)
+                .with_max_level(LevelFilter::Info),
+        );
+        let log = |target: &str, message: &str| {
+            logger.log(&record(
+                target,
+                log::Level::Warn,
+                format_args!("{}", message),
+            ))
+        };
+
+        log("Settings", "secret");
+        log("Noise", "dropped");
+        log("Database", "\x1b[31mred");
+
+        assert_eq!(
+            capture
+                .take()
+                .iter()
+                .map(|e| (e.subsystem.as_str(), e.category.as_str(), e.message_str()))
+                .map(|(s, c, m)| (s.to_owned(), c.to_owned(), m.into_owned()))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "com.example.oslog".into(),
+                    "Settings".into(),
+                    "<private>".into()
+                ),
+                ("com.example.oslog".into(), "Database".into(), "red".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_category_privacy() {
+        let capture = CaptureBackend::new();
+        let logger = logger(
+            Config::default()
+                .with_backend(Arc::new(capture.clone()))
+                .with_max_level(LevelFilter::Info)
+                .with_category_privacy("Auth", Privacy::Private)
+                .with_category_level_filter("Payments", LevelFilter::Warn)
+                .with_category_privacy("Payments", Privacy::Private),
+        );
+        let log = |target: &str, args: std::fmt::Arguments<'_>| {
+            logger.log(&record(target, log::Level::Warn, args))
+        };
+
+        log("Auth", format_args!("{} signed in", "alice"));
+        log("Payments", format_args!("paid {}", crate::Public(42)));
+        log("Rendering", format_args!("{} frames", 60));
+
+        assert_eq!(
+            capture
+                .take()
+                .iter()
+                .map(|e| (e.category.clone(), e.message_str().into_owned()))
+                .collect::<Vec<_>>(),
+            vec![
+                (String::from("Auth"), String::from("<private>")),
+                (String::from("Payments"), String::from("<private>42")),
+                (String::from("Rendering"), String::from("60 frames")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_logging_to_known_category_does_not_allocate() {
+        let logger = logger(
+            Config::default()
+                .with_backend(Arc::new(NullBackend))
+                .with_max_level(LevelFilter::Trace),
+        );
+        let log = |message: &str| {
+            logger.log(&record(
+                "Rendering",
+                log::Level::Debug,
+                format_args!("frame {} took {}", 1, message),
+            ))
+        };
+
+        // The first record creates the category.
+        assert!(crate::buffer::tests::allocations(|| log("3ms")) > 0);
+        assert_eq!(crate::buffer::tests::allocations(|| log("2ms")), 0);
+    }
+
+    #[test]
+    fn test_chunking() {
+        let capture = CaptureBackend::new();
+        let logger = logger(
+            Config::default()
+                .with_backend(Arc::new(capture.clone()))
+                .with_chunk_size(Some(25))
+                .with_category_level_filter("Settings", LevelFilter::Trace),
+        );
+
+        for target in ["Settings", "Other"] {
+            logger.log(&record(
+                target,
+                log::Level::Info,
+                format_args!("{} is far too long to fit", "This"),
+            ));
+        }
+
+        for category in ["Settings", "Other"] {
+            let entries = capture.for_category(category);
+            let messages = crate::chunk::unprefixed(entries.iter().map(|e| e.message_str().into()));
+            assert_eq!(messages, ["This is ", "far too ", "long to ", "fit"]);
+        }
+    }
+
+    #[test]
+    fn test_newlines() {
+        let capture = CaptureBackend::new();
+        let logger = logger(
+            Config::default()
+                .with_backend(Arc::new(capture.clone()))
+                .with_newlines(Newlines::Escape)
+                .with_max_level(LevelFilter::Trace),
+        );
+        logger.log(&record(
+            "Settings",
+            log::Level::Info,
+            format_args!("{}\n{}", "One", "Two"),
+        ));
+
+        capture.assert_logged(Level::Default, "One\\nTwo");
+    }
+
+    #[test]
+    fn test_nul_policy() {
+        let capture = CaptureBackend::new();
+        let logger = logger(
+            Config::default()
+                .with_subsystem("com.example.test".into())
+                .with_backend(Arc::new(capture.clone()))
+                .with_nul_policy(NulPolicy::Reject)
+                .with_max_level(LevelFilter::Trace),
+        );
+        let log = |target: &str, message: &str| {
+            logger.log(&record(
+                target,
+                log::Level::Info,
+                format_args!("{}", message),
+            ))
+        };
+
+        log("Settings", "One\0");
+        log("Settings\0", "Two");
+        log("Settings", "Three");
+
+        assert_eq!(capture.entries().len(), 1);
+        capture.assert_logged(Level::Default, "Three");
+    }
+
+    #[test]
+    fn test_template() {
+        let capture = CaptureBackend::new();
+        let logger = logger(
+            Config::default()
+                .with_backend(Arc::new(capture.clone()))
+                .with_template("[{module}:{line}] {target}: {message}")
+                .with_max_level(LevelFilter::Trace),
+        );
+        logger.log(
+            &Record::builder()
+                .target("Settings")
+                .module_path(Some("app::settings"))
+                .line(Some(12))
+                .level(log::Level::Info)
+                .args(format_args!("{} changed", "Theme"))
+                .build(),
+        );
+
+        capture.assert_logged(Level::Default, "[app::settings:12] Settings: Theme changed");
+    }
+
+    #[test]
+    fn test_control_chars() {
+        let capture = CaptureBackend::new();
+        let logger = logger(
+            Config::default()
+                .with_backend(Arc::new(capture.clone()))
+                .with_control_chars(ControlChars::Strip)
+                .with_template("{target}: {message}")
+                .with_max_level(LevelFilter::Trace),
+        );
+        logger.log(&record(
+            "Settings",
+            log::Level::Info,
+            format_args!("{}\r{}", "\x1b[2KForged", "\u{1F601}"),
+        ));
+
+        capture.assert_logged(Level::Default, "Settings: Forged\u{1F601}");
+    }
+
+    #[test]
+    fn test_private_values() {
+        let capture = CaptureBackend::new();
+        let logger = logger(
+            Config::default()
+                .with_backend(Arc::new(capture.clone()))
+                .with_template("{target}: {message}")
+                .with_max_level(LevelFilter::Trace),
+        );
+        logger.log(&record(
+            "Settings",
+            log::Level::Info,
+            format_args!("user {}", crate::Private("alice")),
+        ));
+
+        capture.assert_logged(Level::Default, "Settings: user <private>");
+    }
+
+    #[test]
+    fn test_private_values_survive_stripping() {
+        let capture = CaptureBackend::new();
+        let logger = logger(
+            Config::default()
+                .with_backend(Arc::new(capture.clone()))
+                .with_control_chars(ControlChars::Strip),
+        );
+        for (start, end) in [("\x1b]", "\x07"), ("\u{9d}", "\u{9c}")] {
+            let private = crate::Private(format!("{}secret-token", end));
+            let args = format_args!("user {}{} done", start, private);
+            logger.log(&record("Settings", log::Level::Info, args));
+        }
+
+        capture.assert_not_logged("user secret-token done");
+        capture.assert_logged(Level::Default, "user <private> done");
+        capture.assert_count(2);
+    }
+
+    #[test]
+    fn test_hash_mask() {
+        let capture = CaptureBackend::new();
+        let mask = HashMask::with_salt(b"salt");
+        let logger = logger(
+            Config::default()
+                .with_backend(Arc::new(capture.clone()))
+                .with_hash_mask(Some(mask))
+                .with_max_level(LevelFilter::Trace),
+        );
+        logger.log(&record(
+            "Settings",
+            log::Level::Info,
+            format_args!("user {}", crate::Private("alice")),
+        ));
+
+        capture.assert_logged(Level::Default, &format!("user {}", mask.mask(b"alice")));
+    }
+
+    #[cfg(feature = "redaction")]
+    #[test]
+    fn test_redaction() {
+        let capture = CaptureBackend::new();
+        let logger = logger(
+            Config::default()
+                .with_backend(Arc::new(capture.clone()))
+                .with_template("{target}: {message}")
+                .with_redaction_rule(r"card=\d+", "card=<redacted>")
+                .with_redaction_preset(RedactionPreset::Email)
+                .with_redaction_preset(RedactionPreset::Ipv4)
+                .with_control_chars(ControlChars::Strip)
+                .with_max_level(LevelFilter::Trace),
+        );
+        let log =
+            |args: std::fmt::Arguments<'_>| logger.log(&record("Billing", log::Level::Info, args));
+
+        log(format_args!(
+            "{} paid with card={}",
+            "bob@example.com", 4242
+        ));
+        log(format_args!(
+            "{} from {}",
+            crate::Private("alice"),
+            "10.0.0.1"
+        ));
+
+        capture.assert_logged(Level::Default, "Billing: <email> paid with card=<redacted>");
+        log(format_args!("contact bob\x1b[m@example.com"));
+
+        capture.assert_logged(Level::Default, "Billing: <private> from <ipv4>");
+        capture.assert_logged(Level::Default, "Billing: contact <email>");
+    }
+
+    #[cfg(feature = "redaction")]
+    #[test]
+    fn test_redaction_keeps_privacy_markers() {
+        let capture = CaptureBackend::new();
+        let logger = logger(
+            Config::default()
+                .with_backend(Arc::new(capture.clone()))
+                .with_redaction_rule(r"^key: .*?a", "")
+                .with_redaction_preset(RedactionPreset::Email),
+        );
+        let log =
+            |args: std::fmt::Arguments<'_>| logger.log(&record("Billing", log::Level::Info, args));
+
+        // Matching across the marker would remove it, making the rest public.
+        log(format_args!("key: {}", crate::Private("a secret")));
+        log(format_args!(
+            "{} wrote to {}",
+            crate::Private("alice"),
+            crate::Public("bob@example.com")
+        ));
+
+        capture.assert_logged(Level::Default, "key: <private>");
+        capture.assert_logged(Level::Default, "<private> wrote to <email>");
+    }
+
+    #[test]
+    fn test_disabled_category() {
+        let capture = CaptureBackend::new();
+        let logger = logger(
+            Config::default()
+                .with_backend(Arc::new(capture.clone()))
+                .with_max_level(LevelFilter::Trace)
+                .with_disabled_category("Noisy"),
+        );
+
+        for target in ["Noisy", "Quiet"] {
+            logger.log(&record(
+                target,
+                log::Level::Error,
+                format_args!("{}", target),
+            ));
+        }
+
+        capture.assert_count(1);
+        capture.assert_not_logged("Noisy");
+    }
 }
@@ -0,0 +1,122 @@
+/// Logs a message whose arguments are individually marked `public(..)` or
+/// `private(..)`. Unmarked arguments are private, matching the unified log's
+/// default for dynamic strings, and the format string itself is public.
+///
+/// Only `{}` placeholders are supported, and each argument must implement
+/// `Display`. The format string and arguments are checked at compile time.
+///
+/// ```
+/// use oslog::{os_log, Level, OsLog};
+///
+/// let log = OsLog::new("com.example.test", "Auth");
+/// let (name, ip) = ("alice", "10.0.0.1");
+/// os_log!(log, Level::Info, "user {} logged in from {}", private(name), public(ip));
+/// ```
+///
+/// Other placeholders, such as `{:x}`, `{0}` or `{name}`, don't compile:
+///
+/// ```compile_fail
+/// # use oslog::{os_log, Level, OsLog};
+/// # let log = OsLog::new("com.example.test", "Auth");
+/// os_log!(log, Level::Info, "{:x}", public(255));
+/// ```
+///
+/// ```compile_fail
+/// # use oslog::{os_log, Level, OsLog};
+/// # let log = OsLog::new("com.example.test", "Auth");
+/// let name = "alice";
+/// os_log!(log, Level::Info, "{name}");
+/// ```
+///
+/// On Apple platforms private arguments are shown as `<private>` unless
+/// private data logging is enabled. Other backends always show `<private>`.
+#[macro_export]
+macro_rules! os_log {
+    ($log:expr, $level:expr, $format:literal $(, $($args:tt)*)?) => {
+        $crate::__os_log_args!(($log, $level, $format) [] $($($args)*)?)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __os_log_args {
+    ($head:tt [$($out:tt)*] public($arg:expr) $(, $($rest:tt)*)?) => {
+        $crate::__os_log_args!($head [$($out)* ($crate::Privacy::Public, $arg)] $($($rest)*)?)
+    };
+    ($head:tt [$($out:tt)*] private($arg:expr) $(, $($rest:tt)*)?) => {
+        $crate::__os_log_args!($head [$($out)* ($crate::Privacy::Private, $arg)] $($($rest)*)?)
+    };
+    ($head:tt [$($out:tt)*] $arg:expr $(, $($rest:tt)*)?) => {
+        $crate::__os_log_args!($head [$($out)* ($crate::Privacy::Private, $arg)] $($($rest)*)?)
+    };
+    (($log:expr, $level:expr, $format:literal) [$(($privacy:expr, $arg:expr))*]) => {{
+        const _: () = $crate::__private::check_os_log_format($format);
+        if false {
+            let _ = ::core::format_args!($format, $($arg),*);
+        }
+        $crate::OsLog::with_privacy(
+            &$log,
+            $level,
+            $format,
+            &[$(($privacy, &$arg as &dyn ::core::fmt::Display)),*],
+        );
+    }};
+}
+
+/// Logs a message formatted like `format!` at the given level. The message is
+/// formatted straight into the buffer passed to the backend, and not at all if
+/// the level isn't enabled.
+///
+/// ```
+/// use oslog::{os_log_fmt, Level, OsLog};
+///
+/// let log = OsLog::new("com.example.test", "Parsing");
+/// let line = 12;
+/// os_log_fmt!(log, Level::Error, "Unexpected token on line {}", line);
+/// ```
+#[macro_export]
+macro_rules! os_log_fmt {
+    ($log:expr, $level:expr, $($arg:tt)+) => {
+        $crate::OsLog::log_fmt(&$log, $level, ::core::format_args!($($arg)+))
+    };
+}
+
+/// `os_log_fmt!` at `Level::Debug`.
+#[macro_export]
+macro_rules! os_log_debug {
+    ($log:expr, $($arg:tt)+) => {
+        $crate::os_log_fmt!($log, $crate::Level::Debug, $($arg)+)
+    };
+}
+
+/// `os_log_fmt!` at `Level::Info`.
+#[macro_export]
+macro_rules! os_log_info {
+    ($log:expr, $($arg:tt)+) => {
+        $crate::os_log_fmt!($log, $crate::Level::Info, $($arg)+)
+    };
+}
+
+/// `os_log_fmt!` at `Level::Default`.
+#[macro_export]
+macro_rules! os_log_default {
+    ($log:expr, $($arg:tt)+) => {
+        $crate::os_log_fmt!($log, $crate::Level::Default, $($arg)+)
+    };
+}
+
+/// `os_log_fmt!` at `Level::Error`.
+#[macro_export]
+macro_rules! os_log_error {
+    ($log:expr, $($arg:tt)+) => {
+        $crate::os_log_fmt!($log, $crate::Level::Error, $($arg)+)
+    };
+}
+
+/// `os_log_fmt!` at `Level::Fault`.
+#[macro_export]
+macro_rules! os_log_fault {
+    ($log:expr, $($arg:tt)+) => {
+        $crate::os_log_fmt!($log, $crate::Level::Fault, $($arg)+)
+    };
+}
@@ -0,0 +1,285 @@
+//! Replacing private values with salted hashes, so entries about the same
+//! value can be correlated without revealing it.
+
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::hash::BuildHasher;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const SALT_LEN: usize = 16;
+
+/// Replaces private values with a hash like
+/// `<mask.hash: 'rG4kRxE6KqJmGH6hH4JwEw=='>`, as the unified log's
+/// `mask.hash` modifier does. The same value always gives the same hash for
+/// the same salt.
+///
+/// Applies to `os_log!` and messages with `Private` values. Arguments to
+/// `os_log_static!` keep their privacy.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HashMask {
+    key: (u64, u64),
+}
+
+/// The key would let anyone reading the log reverse hashes by guessing.
+impl fmt::Debug for HashMask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HashMask").finish_non_exhaustive()
+    }
+}
+
+impl HashMask {
+    /// Uses a fixed salt, e.g. one stored in the app's settings.
+    pub fn with_salt(salt: &[u8]) -> Self {
+        Self {
+            key: (
+                siphash((0x6f736c6f67, 0), salt),
+                siphash((0x6f736c6f67, 1), salt),
+            ),
+        }
+    }
+
+    /// Uses a random salt which is the same for the rest of the process, so
+    /// hashes can't be correlated across launches.
+    pub fn per_process() -> Self {
+        static SALT: OnceLock<[u8; 16]> = OnceLock::new();
+        Self::with_salt(SALT.get_or_init(random_salt))
+    }
+
+    /// Uses a random salt stored at `path`, creating it if it doesn't exist,
+    /// so hashes are stable for as long as the app stays installed.
+    ///
+    /// The file is only readable by its owner on Unix. If it doesn't hold a
+    /// salt of the right length, e.g. because it was truncated, it's replaced
+    /// with a new one.
+    pub fn per_install(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+
+        let existing = match fs::read(path) {
+            Ok(salt) if salt.len() == SALT_LEN => return Ok(Self::with_salt(&salt)),
+            Ok(_) => true,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => false,
+            Err(err) => return Err(err),
+        };
+
+        // The salt is written to a temporary file first, so other processes
+        // never see a partly written one.
+        let temp = write_temp_salt(path)?;
+        let installed = if existing {
+            fs::rename(&temp, path)
+        } else {
+            // Linking fails if another process created the file first, in
+            // which case its salt is used.
+            match fs::hard_link(&temp, path) {
+                Err(err) if err.kind() != io::ErrorKind::AlreadyExists => fs::rename(&temp, path),
+                _ => Ok(()),
+            }
+        };
+        let _ = fs::remove_file(&temp);
+        installed?;
+
+        let salt = fs::read(path)?;
+        if salt.len() != SALT_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "hash mask salt file has the wrong length",
+            ));
+        }
+        Ok(Self::with_salt(&salt))
+    }
+
+    /// Returns the mask for `value`.
+    pub fn mask(&self, value: &[u8]) -> String {
+        let (k0, k1) = self.key;
+        let mut hash = [0; 16];
+        hash[..8].copy_from_slice(&siphash((k0, k1), value).to_le_bytes());
+        hash[8..].copy_from_slice(&siphash((k1, k0), value).to_le_bytes());
+
+        format!("<mask.hash: '{}'>", base64(&hash))
+    }
+}
+
+/// Writes a new salt to a uniquely named file next to `path`.
+fn write_temp_salt(path: &Path) -> io::Result<PathBuf> {
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(format!(
+        ".{:016x}.tmp",
+        RandomState::new().hash_one(std::process::id())
+    ));
+    let temp = path.with_file_name(name);
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let mut file = options.open(&temp)?;
+    let written = file.write_all(&random_salt()).and_then(|_| file.sync_all());
+    if let Err(err) = written {
+        let _ = fs::remove_file(&temp);
+        return Err(err);
+    }
+    Ok(temp)
+}
+
+/// `RandomState` is seeded by the OS's random number generator.
+fn random_salt() -> [u8; SALT_LEN] {
+    let state = RandomState::new();
+    let mut salt = [0; 16];
+    salt[..8].copy_from_slice(&state.hash_one(0u8).to_le_bytes());
+    salt[8..].copy_from_slice(&state.hash_one(1u8).to_le_bytes());
+    salt
+}
+
+/// SipHash-2-4.
+fn siphash((k0, k1): (u64, u64), data: &[u8]) -> u64 {
+    let mut v = [
+        k0 ^ 0x736f6d6570736575,
+        k1 ^ 0x646f72616e646f6d,
+        k0 ^ 0x6c7967656e657261,
+        k1 ^ 0x7465646279746573,
+    ];
+
+    let round = |v: &mut [u64; 4]| {
+        v[0] = v[0].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(13) ^ v[0];
+        v[0] = v[0].rotate_left(32);
+        v[2] = v[2].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(16) ^ v[2];
+        v[0] = v[0].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(21) ^ v[0];
+        v[2] = v[2].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(17) ^ v[2];
+        v[2] = v[2].rotate_left(32);
+    };
+    let mut compress = |m: u64| {
+        v[3] ^= m;
+        round(&mut v);
+        round(&mut v);
+        v[0] ^= m;
+    };
+
+    let mut chunks = data.chunks_exact(8);
+    for chunk in &mut chunks {
+        compress(u64::from_le_bytes(chunk.try_into().unwrap()));
+    }
+
+    let mut last = [0; 8];
+    last[..chunks.remainder().len()].copy_from_slice(chunks.remainder());
+    compress(u64::from_le_bytes(last) | (data.len() as u64) << 56);
+
+    v[2] ^= 0xff;
+    for _ in 0..4 {
+        round(&mut v);
+    }
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, byte)| n | (*byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_siphash() {
+        // Test vectors from the SipHash paper's reference implementation.
+        let key = (0x0706050403020100, 0x0f0e0d0c0b0a0908);
+        let data: Vec<u8> = (0..64).collect();
+        assert_eq!(siphash(key, &data[..0]), 0x726fdb47dd0e0e31);
+        assert_eq!(siphash(key, &data[..1]), 0x74f839c593dc67fd);
+        assert_eq!(siphash(key, &data[..8]), 0x93f5f5799a932462);
+        assert_eq!(siphash(key, &data[..15]), 0xa129ca6149be45e5);
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn test_mask() {
+        let mask = HashMask::with_salt(b"salt");
+        let hash = mask.mask(b"alice");
+        assert!(hash.starts_with("<mask.hash: '"), "{}", hash);
+        assert!(hash.ends_with("=='>"), "{}", hash);
+        assert_eq!(hash.len(), "<mask.hash: ''>".len() + 24);
+
+        assert_eq!(mask.mask(b"alice"), hash);
+        assert_ne!(mask.mask(b"bob"), hash);
+        assert_ne!(HashMask::with_salt(b"pepper").mask(b"alice"), hash);
+    }
+
+    #[test]
+    fn test_per_process() {
+        assert_eq!(HashMask::per_process(), HashMask::per_process());
+    }
+
+    #[test]
+    fn test_per_install() {
+        let path = std::env::temp_dir().join(format!("oslog-salt-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mask = HashMask::per_install(&path).unwrap();
+        assert_eq!(fs::read(&path).unwrap().len(), 16);
+        assert_eq!(HashMask::per_install(&path).unwrap(), mask);
+        assert_ne!(mask, HashMask::per_process());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_per_install_replaces_bad_salts() {
+        let path = std::env::temp_dir().join(format!("oslog-bad-salt-{}", std::process::id()));
+
+        for bad in [&b""[..], b"short", &[0; 32]] {
+            fs::write(&path, bad).unwrap();
+            let mask = HashMask::per_install(&path).unwrap();
+
+            let salt = fs::read(&path).unwrap();
+            assert_eq!(salt.len(), 16);
+            assert_ne!(salt, [0; 16]);
+            assert_eq!(mask, HashMask::with_salt(&salt));
+            assert_eq!(HashMask::per_install(&path).unwrap(), mask);
+        }
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_debug_hides_key() {
+        let mask = HashMask::with_salt(b"salt");
+        assert_eq!(format!("{:?}", mask), "HashMask { .. }");
+    }
+}
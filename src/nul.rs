@@ -0,0 +1,86 @@
+//! Handling of nul bytes, which can't be passed to the unified log.
+
+use std::ffi::CString;
+
+/// What to do with nul bytes in messages, subsystems and categories.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NulPolicy {
+    /// Replace each nul byte with the string, which mustn't contain nul bytes
+    /// itself. The default replaces them with `(null)`.
+    Replace(&'static str),
+    /// Replace each nul byte with `\0`.
+    Escape,
+    /// Replace each nul byte with `\u{0}`, as Rust's `Debug` output does.
+    EscapeUnicode,
+    /// Drop everything from the first nul byte on.
+    Truncate,
+    /// Refuse the whole string. Messages are dropped, or reported by
+    /// `OsLog::try_with_level`.
+    Reject,
+}
+
+impl Default for NulPolicy {
+    fn default() -> Self {
+        Self::Replace("(null)")
+    }
+}
+
+impl NulPolicy {
+    /// What each nul byte is replaced with, if it's replaced at all.
+    #[inline]
+    pub(crate) fn replacement(&self) -> Option<&'static str> {
+        match self {
+            Self::Replace(replacement) => Some(replacement),
+            Self::Escape => Some("\\0"),
+            Self::EscapeUnicode => Some("\\u{0}"),
+            Self::Truncate | Self::Reject => None,
+        }
+    }
+
+    /// Applies the policy to `text`, returning the position of the first nul
+    /// byte if it's rejected.
+    pub(crate) fn apply(&self, text: &str) -> Result<CString, usize> {
+        let Some(position) = text.find('\0') else {
+            return Ok(CString::new(text).unwrap());
+        };
+
+        let fixed = match self.replacement() {
+            Some(replacement) => text.replace('\0', &replacement.replace('\0', "")),
+            None if *self == Self::Truncate => text[..position].to_owned(),
+            None => return Err(position),
+        };
+        Ok(CString::new(fixed).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let cases = [
+            (NulPolicy::default(), Ok("a(null)b(null)")),
+            (NulPolicy::Replace("?"), Ok("a?b?")),
+            (NulPolicy::Replace("\0!"), Ok("a!b!")),
+            (NulPolicy::Escape, Ok("a\\0b\\0")),
+            (NulPolicy::EscapeUnicode, Ok("a\\u{0}b\\u{0}")),
+            (NulPolicy::Truncate, Ok("a")),
+            (NulPolicy::Reject, Err(1)),
+        ];
+
+        for (policy, expected) in cases {
+            let actual = policy.apply("a\0b\0");
+            let actual = actual.as_ref().map(|s| s.to_str().unwrap());
+            assert_eq!(actual, expected.as_deref(), "{:?}", policy);
+        }
+    }
+
+    #[test]
+    fn test_without_nul_bytes() {
+        assert_eq!(
+            NulPolicy::Reject.apply("\u{1F601}").unwrap().to_bytes(),
+            "\u{1F601}".as_bytes()
+        );
+    }
+}
@@ -0,0 +1,63 @@
+//! Parsing of the `{name}` placeholders in message templates and stderr line
+//! formats.
+
+/// A run of literal text or a placeholder in a parsed template.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Segment<T> {
+    Text(String),
+    Placeholder(T),
+}
+
+/// Splits `template` into text and the placeholders named in `placeholders`,
+/// e.g. `("{message}", ..)`. `{{` and `}}` produce literal braces, and unknown
+/// placeholders are kept as text.
+pub(crate) fn parse<T: Copy>(template: &str, placeholders: &[(&str, T)]) -> Vec<Segment<T>> {
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    let mut rest = template;
+
+    while let Some(c) = rest.chars().next() {
+        let placeholder = placeholders.iter().find(|(name, _)| rest.starts_with(name));
+
+        if let Some((name, placeholder)) = placeholder {
+            if !text.is_empty() {
+                segments.push(Segment::Text(std::mem::take(&mut text)));
+            }
+            segments.push(Segment::Placeholder(*placeholder));
+            rest = &rest[name.len()..];
+        } else if rest.starts_with("{{") || rest.starts_with("}}") {
+            text.push(c);
+            rest = &rest[2..];
+        } else {
+            text.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let placeholders = [("{a}", 1), ("{ab}", 2)];
+        assert_eq!(
+            parse("{{{a}}}:{ab} {unknown} }", &placeholders),
+            [
+                Segment::Text(String::from("{")),
+                Segment::Placeholder(1),
+                Segment::Text(String::from("}:")),
+                Segment::Placeholder(2),
+                Segment::Text(String::from(" {unknown} }")),
+            ]
+        );
+        assert!(parse("", &placeholders).is_empty());
+    }
+}
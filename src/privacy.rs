@@ -0,0 +1,424 @@
+//! Wrappers marking values as public or private within formatted messages.
+//!
+//! While a message is formatted for logging, the wrappers surround their
+//! output with markers which are then used to split the message into public
+//! and private parts. Anywhere else a `Private` value formats as
+//! `<private>`, so it can't leak through other formatting.
+//!
+//! Each marker is a noncharacter followed by a random nonce, also made of
+//! noncharacters, which is chosen once per process. Any other characters in
+//! that range are dropped when splitting, so text in a message can't forge a
+//! boundary.
+
+use crate::{HashMask, Privacy};
+use std::cell::Cell;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::sync::OnceLock;
+use std::thread::LocalKey;
+
+const START_PRIVATE: char = '\u{FDD0}';
+const START_PUBLIC: char = '\u{FDD1}';
+const END: char = '\u{FDD2}';
+
+/// The nonce is written with one of these per nibble.
+const NONCE_DIGITS: u32 = 0xFDE0;
+
+/// Markers and nonces only use noncharacters from this range.
+pub(crate) fn is_reserved(c: char) -> bool {
+    ('\u{FDD0}'..='\u{FDEF}').contains(&c)
+}
+
+fn nonce() -> &'static str {
+    static NONCE: OnceLock<String> = OnceLock::new();
+    NONCE.get_or_init(|| {
+        let random = RandomState::new().hash_one(0u8);
+        (0..16)
+            .map(|i| char::from_u32(NONCE_DIGITS + (random >> (i * 4) & 0xf) as u32).unwrap())
+            .collect()
+    })
+}
+
+thread_local! {
+    static CAPTURING: Cell<bool> = const { Cell::new(false) };
+    static MARKED: Cell<bool> = const { Cell::new(false) };
+    static CURRENT: Cell<Option<Privacy>> = const { Cell::new(None) };
+    static MASK: Cell<Option<HashMask>> = const { Cell::new(None) };
+}
+
+/// Sets a thread local until dropped, restoring its previous value even if
+/// formatting panics.
+struct Scoped<T: Copy + 'static> {
+    key: &'static LocalKey<Cell<T>>,
+    previous: T,
+}
+
+impl<T: Copy + 'static> Scoped<T> {
+    fn set(key: &'static LocalKey<Cell<T>>, value: T) -> Self {
+        let previous = key.with(|cell| cell.replace(value));
+        Self { key, previous }
+    }
+}
+
+impl<T: Copy + 'static> Drop for Scoped<T> {
+    fn drop(&mut self) {
+        let _ = self.key.try_with(|cell| cell.set(self.previous));
+    }
+}
+
+/// Logs the value as private data, e.g.
+/// `info!("signed in as {}", Private(&name))`. Private values nested within
+/// public ones stay private.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Private<T>(pub T);
+
+/// Logs the value as public data, e.g. within a log whose default privacy is
+/// private.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Public<T>(pub T);
+
+macro_rules! impl_fmt {
+    ($wrapper:ident, $privacy:expr, $($trait:ident),*) => {
+        $(
+            impl<T: fmt::$trait> fmt::$trait for $wrapper<T> {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    write_marked(f, $privacy, |f| fmt::$trait::fmt(&self.0, f))
+                }
+            }
+        )*
+    };
+}
+
+impl_fmt!(Private, Privacy::Private, Display, Debug);
+impl_fmt!(Public, Privacy::Public, Display, Debug);
+
+/// Logs a salted hash of the value as public data, so entries about the same
+/// value can be correlated without revealing it. The hash uses the log's
+/// `HashMask` if it has one, and `HashMask::per_process()` otherwise.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Hashed<T>(pub T);
+
+impl<T: fmt::Display> fmt::Display for Hashed<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_hashed(f, format_args!("{}", self.0))
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Hashed<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_hashed(f, format_args!("{:?}", self.0))
+    }
+}
+
+fn write_hashed(f: &mut fmt::Formatter<'_>, value: fmt::Arguments<'_>) -> fmt::Result {
+    // Nested `Private` values are hashed as `<private>` rather than leaking
+    // markers into the hash.
+    let text = {
+        let _capturing = Scoped::set(&CAPTURING, false);
+        value.to_string()
+    };
+
+    let mask = MASK.with(Cell::get).unwrap_or_else(HashMask::per_process);
+    let masked = mask.mask(text.as_bytes());
+    write_marked(f, Privacy::Public, |f| f.write_str(&masked))
+}
+
+/// A value with fields which must not be logged in the clear. Usually
+/// implemented with `#[derive(OsLogValue)]`, which also implements `Debug`
+/// with `fmt_sensitive`.
+pub trait Sensitive {
+    /// Formats the value like `Debug`, with sensitive fields wrapped in
+    /// `Private` or `Hashed` and skipped fields left out.
+    fn fmt_sensitive(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+}
+
+impl<T: Sensitive + ?Sized> Sensitive for &T {
+    fn fmt_sensitive(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt_sensitive(f)
+    }
+}
+
+fn write_marked<F>(f: &mut fmt::Formatter<'_>, privacy: Privacy, value: F) -> fmt::Result
+where
+    F: FnOnce(&mut fmt::Formatter<'_>) -> fmt::Result,
+{
+    if !CAPTURING.with(Cell::get) {
+        return match privacy {
+            Privacy::Private => f.write_str("<private>"),
+            Privacy::Public => value(f),
+        };
+    }
+
+    // Within a private value everything is private, and within a public one
+    // only private values change anything.
+    let current = CURRENT.with(Cell::get);
+    if current == Some(privacy) || current == Some(Privacy::Private) {
+        return value(f);
+    }
+
+    MARKED.with(|marked| marked.set(true));
+    let _current = Scoped::set(&CURRENT, Some(privacy));
+    let start = match privacy {
+        Privacy::Private => START_PRIVATE,
+        Privacy::Public => START_PUBLIC,
+    };
+    let end = match current {
+        Some(Privacy::Public) => START_PUBLIC,
+        _ => END,
+    };
+
+    write_marker(f, start)
+        .and_then(|_| value(f))
+        .and_then(|_| write_marker(f, end))
+}
+
+fn write_marker(f: &mut fmt::Formatter<'_>, marker: char) -> fmt::Result {
+    fmt::Write::write_char(f, marker)?;
+    f.write_str(nonce())
+}
+
+/// Runs `format` with the wrappers writing markers, returning whether any
+/// did. `Hashed` values are hashed with `mask` if given.
+pub(crate) fn capture<F: FnOnce()>(mask: Option<HashMask>, format: F) -> bool {
+    let _capturing = Scoped::set(&CAPTURING, true);
+    let _marked = Scoped::set(&MARKED, false);
+    let _current = Scoped::set(&CURRENT, None);
+    let _mask = Scoped::set(&MASK, mask);
+
+    format();
+
+    MARKED.with(Cell::get)
+}
+
+/// Splits text written by `capture` into runs of public and private text.
+/// Unmarked text has the `default` privacy. Adjacent runs always differ in
+/// privacy. Reserved characters which aren't part of a marker are dropped.
+pub(crate) fn split_marked(text: &str, default: Privacy) -> Vec<(Privacy, String)> {
+    let mut parts: Vec<(Privacy, String)> = Vec::new();
+    let mut privacy = default;
+    let mut push = |privacy: Privacy, text: &str| match parts.last_mut() {
+        _ if text.is_empty() => {}
+        Some((last, buffer)) if *last == privacy => buffer.push_str(text),
+        _ => parts.push((privacy, text.to_owned())),
+    };
+
+    let nonce = nonce();
+    let mut rest = text;
+    while let Some(index) = rest.find(is_reserved) {
+        push(privacy, &rest[..index]);
+
+        let c = rest[index..].chars().next().unwrap();
+        let after = &rest[index + c.len_utf8()..];
+        rest = match after.strip_prefix(nonce) {
+            Some(after) if matches!(c, START_PRIVATE | START_PUBLIC | END) => {
+                privacy = match c {
+                    START_PRIVATE => Privacy::Private,
+                    START_PUBLIC => Privacy::Public,
+                    _ => default,
+                };
+                after
+            }
+            _ => after,
+        };
+    }
+    push(privacy, rest);
+
+    parts
+}
+
+/// Replaces each run of text between the markers in text written by
+/// `capture` with `f` of it, keeping the markers, so `f` can't move or remove
+/// a boundary. Reserved characters which aren't part of a marker are dropped.
+#[cfg(feature = "redaction")]
+pub(crate) fn map_marked<F>(text: &str, mut f: F) -> String
+where
+    F: FnMut(&str) -> std::borrow::Cow<'_, str>,
+{
+    let mut mapped = String::with_capacity(text.len());
+    let mut run = String::new();
+
+    let nonce = nonce();
+    let mut rest = text;
+    while let Some(index) = rest.find(is_reserved) {
+        run.push_str(&rest[..index]);
+
+        let c = rest[index..].chars().next().unwrap();
+        let after = &rest[index + c.len_utf8()..];
+        rest = match after.strip_prefix(nonce) {
+            Some(after) if matches!(c, START_PRIVATE | START_PUBLIC | END) => {
+                mapped.push_str(&f(&run));
+                run.clear();
+                mapped.push(c);
+                mapped.push_str(nonce);
+                after
+            }
+            _ => after,
+        };
+    }
+    run.push_str(rest);
+    mapped.push_str(&f(&run));
+
+    mapped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn captured(args: fmt::Arguments<'_>) -> (String, bool) {
+        let mut text = String::new();
+        let marked = capture(None, || {
+            let _ = fmt::Write::write_fmt(&mut text, args);
+        });
+        (text, marked)
+    }
+
+    #[test]
+    fn test_outside_capture() {
+        assert_eq!(Private("secret").to_string(), "<private>");
+        assert_eq!(format!("{:?}", Private("secret")), "<private>");
+        assert_eq!(Public(42).to_string(), "42");
+        assert_eq!(format!("{:?}", Public("a")), "\"a\"");
+    }
+
+    #[test]
+    fn test_capture() {
+        let (text, marked) = captured(format_args!("a {} b {:?}", Private(1), Public("c")));
+        assert!(marked);
+        assert_eq!(
+            split_marked(&text, Privacy::Public),
+            [
+                (Privacy::Public, String::from("a ")),
+                (Privacy::Private, String::from("1")),
+                (Privacy::Public, String::from(" b \"c\"")),
+            ]
+        );
+        assert_eq!(
+            split_marked(&text, Privacy::Private),
+            [
+                (Privacy::Private, String::from("a 1 b ")),
+                (Privacy::Public, String::from("\"c\"")),
+            ]
+        );
+
+        let (text, marked) = captured(format_args!("{}", 1));
+        assert!(!marked);
+        assert_eq!(text, "1");
+    }
+
+    #[test]
+    fn test_markers_cant_be_forged() {
+        let (text, _) = captured(format_args!(
+            "{} {}{}",
+            "\u{FDD1}card 4111-1111",
+            Public(1),
+            "\u{FDD2}\u{FDE0} tail"
+        ));
+        assert_eq!(
+            split_marked(&text, Privacy::Private),
+            [
+                (Privacy::Private, String::from("card 4111-1111 ")),
+                (Privacy::Public, String::from("1")),
+                (Privacy::Private, String::from(" tail")),
+            ]
+        );
+    }
+
+    #[cfg(feature = "redaction")]
+    #[test]
+    fn test_map_marked() {
+        let (text, _) = captured(format_args!("a{} {}\u{FDD5}c", Private("bb"), Public("b")));
+        let mapped = map_marked(&text, |run| run.replace('b', "x").into());
+        assert_eq!(
+            split_marked(&mapped, Privacy::Public),
+            [
+                (Privacy::Public, String::from("a")),
+                (Privacy::Private, String::from("xx")),
+                (Privacy::Public, String::from(" xc")),
+            ]
+        );
+
+        // Runs are mapped whole, without forged markers splitting them.
+        let (text, _) = captured(format_args!("{} b\u{FDD5}c", Private(1)));
+        let mapped = map_marked(&text, |run| run.replace(" bc", "!").into());
+        assert_eq!(
+            split_marked(&mapped, Privacy::Public),
+            [
+                (Privacy::Private, String::from("1")),
+                (Privacy::Public, String::from("!")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_panic_while_capturing() {
+        struct Panics;
+
+        impl fmt::Display for Panics {
+            fn fmt(&self, _: &mut fmt::Formatter<'_>) -> fmt::Result {
+                panic!("formatting failed");
+            }
+        }
+
+        let result = std::panic::catch_unwind(|| {
+            captured(format_args!("{} {}", Private("secret"), Public(Panics)))
+        });
+        assert!(result.is_err());
+
+        assert_eq!(format!("{}", Private("secret")), "<private>");
+        assert!(!captured(format_args!("{}", 1)).1);
+    }
+
+    #[test]
+    fn test_hashed() {
+        let mask = HashMask::with_salt(b"salt");
+        let mut text = String::new();
+        capture(Some(mask), || {
+            let _ = fmt::Write::write_fmt(
+                &mut text,
+                format_args!("{} {:?}", Hashed("alice"), Private(Hashed("bob"))),
+            );
+        });
+        assert_eq!(
+            split_marked(&text, Privacy::Private),
+            [
+                (Privacy::Public, mask.mask(b"alice")),
+                (Privacy::Private, format!(" {}", mask.mask(b"\"bob\""))),
+            ]
+        );
+
+        let per_process = HashMask::per_process();
+        assert_eq!(
+            Hashed(Private("x")).to_string(),
+            per_process.mask(b"<private>")
+        );
+    }
+
+    #[test]
+    fn test_nested() {
+        struct Account(u32);
+
+        impl fmt::Display for Account {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "account {} ({})", self.0, Private("owner"))
+            }
+        }
+
+        let (text, _) = captured(format_args!("{}", Public(Account(7))));
+        assert_eq!(
+            split_marked(&text, Privacy::Private),
+            [
+                (Privacy::Public, String::from("account 7 (")),
+                (Privacy::Private, String::from("owner")),
+                (Privacy::Public, String::from(")")),
+            ]
+        );
+
+        let (text, _) = captured(format_args!("{}", Private(Public(1))));
+        assert_eq!(
+            split_marked(&text, Privacy::Public),
+            [(Privacy::Private, String::from("1"))]
+        );
+    }
+}
@@ -0,0 +1,66 @@
+//! The process wide logs handed out by [`OsLog::shared`].
+
+use crate::{Error, OsLog};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+type Registry = HashMap<String, HashMap<String, OsLog>>;
+
+fn registry() -> &'static Mutex<Registry> {
+    static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+    REGISTRY.get_or_init(Mutex::default)
+}
+
+/// Returns a clone of the log registered for `subsystem` and `category`,
+/// registering the result of `create` if there isn't one yet.
+pub(crate) fn get_or_try_insert<F>(
+    subsystem: &str,
+    category: &str,
+    create: F,
+) -> Result<OsLog, Error>
+where
+    F: FnOnce() -> Result<OsLog, Error>,
+{
+    let mut registry = registry().lock().unwrap_or_else(|e| e.into_inner());
+
+    if let Some(log) = registry.get(subsystem).and_then(|logs| logs.get(category)) {
+        return Ok(log.clone());
+    }
+
+    let log = create()?;
+    registry
+        .entry(subsystem.into())
+        .or_default()
+        .insert(category.into(), log.clone());
+
+    Ok(log)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reuses_logs() {
+        let mut created = 0;
+        for _ in 0..3 {
+            get_or_try_insert("com.example.registry", "reused", || {
+                created += 1;
+                OsLog::try_new("com.example.registry", "reused")
+            })
+            .unwrap();
+        }
+        assert_eq!(created, 1);
+    }
+
+    #[test]
+    fn test_errors_are_not_registered() {
+        let result = get_or_try_insert("com.example.registry", "failed", || Err(Error::NullHandle));
+        assert_eq!(result.err(), Some(Error::NullHandle));
+
+        let result = get_or_try_insert("com.example.registry", "failed", || {
+            OsLog::try_new("com.example.registry", "failed")
+        });
+        assert!(result.is_ok());
+    }
+}
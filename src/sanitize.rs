@@ -0,0 +1,268 @@
+//! Removing or escaping control characters and ANSI escape sequences, so
+//! messages can't garble terminals or forge log lines.
+
+use std::borrow::Cow;
+
+/// How control characters and ANSI escape sequences in messages are handled.
+/// Tabs and newlines are left alone, as are nul bytes, which follow the
+/// `NulPolicy`. C1 controls such as U+0085 (next line) and U+009B (an 8-bit
+/// CSI) are handled like the others.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ControlChars {
+    /// Log them as they are.
+    #[default]
+    Keep,
+    /// Remove control characters, along with whole CSI sequences such as
+    /// colours (`ESC [ ... m`) and OSC sequences such as window titles
+    /// (`ESC ] ... BEL`).
+    Strip,
+    /// Replace control characters with escapes like `\x1b` or `\u{9b}`,
+    /// leaving the rest of any escape sequence visible.
+    Escape,
+}
+
+impl ControlChars {
+    /// Applies the setting to a whole string.
+    pub(crate) fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if *self == Self::Keep || !has_control(text) {
+            return Cow::Borrowed(text);
+        }
+
+        let mut sanitized = String::with_capacity(text.len());
+        Sanitizer::new(*self).sanitize(text, |run| sanitized.push_str(run));
+        Cow::Owned(sanitized)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    Text,
+    /// After an `ESC`.
+    Escape,
+    /// Within `ESC [` or a C1 CSI.
+    Csi,
+    /// Within `ESC ]` or a C1 OSC.
+    Osc,
+    /// After an `ESC` within an OSC sequence, which `\` terminates.
+    OscEscape,
+}
+
+/// Sanitizes text which may arrive in pieces, as when formatting, so escape
+/// sequences can span several calls.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Sanitizer {
+    mode: ControlChars,
+    state: State,
+}
+
+impl Sanitizer {
+    pub(crate) fn new(mode: ControlChars) -> Self {
+        Self {
+            mode,
+            state: State::Text,
+        }
+    }
+
+    pub(crate) fn is_active(&self) -> bool {
+        self.mode != ControlChars::Keep
+    }
+
+    /// Calls `push` with each run of text to keep.
+    pub(crate) fn sanitize<F: FnMut(&str)>(&mut self, text: &str, mut push: F) {
+        if self.state == State::Text && !has_control(text) {
+            return push(text);
+        }
+
+        let mut start = 0;
+        for (index, c) in text.char_indices() {
+            if self.state == State::Text && !is_control_char(c) {
+                continue;
+            }
+
+            let action = self.next(c);
+            if action == Action::Keep {
+                continue;
+            }
+
+            if start < index {
+                push(&text[start..index]);
+            }
+            start = index + c.len_utf8();
+
+            if action == Action::Escape {
+                push(escape(c, &mut [0; 6]));
+            }
+        }
+
+        if start < text.len() {
+            push(&text[start..]);
+        }
+    }
+
+    /// Advances past a control character or one within an escape sequence.
+    fn next(&mut self, c: char) -> Action {
+        if self.mode == ControlChars::Escape {
+            return Action::Escape;
+        }
+
+        match (self.state, c) {
+            // Privacy markers end any sequence and are kept, so a sequence
+            // can't swallow the boundary of a `Private` value.
+            (_, c) if crate::privacy::is_reserved(c) => {
+                self.state = State::Text;
+                return Action::Keep;
+            }
+            (State::Text, '\x1b') => self.state = State::Escape,
+            (State::Text, '\u{9b}') => self.state = State::Csi,
+            (State::Text, '\u{9d}') => self.state = State::Osc,
+            (State::Text, _) => {}
+            (State::Escape, '[') => self.state = State::Csi,
+            (State::Escape, ']') => self.state = State::Osc,
+            (State::Escape, ' '..='~') => self.state = State::Text,
+            (State::Csi, ' '..='?') => {}
+            (State::Csi, '@'..='~') => self.state = State::Text,
+            (State::Osc, '\x07' | '\u{9c}') => self.state = State::Text,
+            (State::Osc, '\x1b') => self.state = State::OscEscape,
+            (State::Osc, _) => {}
+            (State::OscEscape, '\\') => self.state = State::Text,
+            // Anything else ends the sequence early and is handled as text.
+            (State::Escape | State::Csi | State::OscEscape, _) => {
+                self.state = State::Text;
+                if !is_control_char(c) {
+                    return Action::Keep;
+                }
+                return self.next(c);
+            }
+        }
+
+        Action::Drop
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Action {
+    Keep,
+    Drop,
+    Escape,
+}
+
+/// Whether `c` is a C0 control character other than tab, newline and nul,
+/// delete, or a C1 control character.
+fn is_control_char(c: char) -> bool {
+    (c.is_ascii() && is_control(c as u8)) || ('\u{80}'..='\u{9f}').contains(&c)
+}
+
+fn is_control(byte: u8) -> bool {
+    matches!(byte, 0x01..=0x08 | 0x0b..=0x1f | 0x7f)
+}
+
+/// Whether `text` has any characters `is_control_char` matches. C1 controls
+/// are encoded as `0xc2` followed by `0x80` to `0x9f`.
+fn has_control(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    bytes.iter().enumerate().any(|(i, &byte)| {
+        is_control(byte) || (byte == 0xc2 && matches!(bytes.get(i + 1), Some(0x80..=0x9f)))
+    })
+}
+
+fn escape(c: char, buffer: &mut [u8; 6]) -> &str {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    let code = c as usize;
+    let (high, low) = (HEX[(code >> 4) & 0xf], HEX[code & 0xf]);
+    let len = if c.is_ascii() {
+        buffer[..4].copy_from_slice(&[b'\\', b'x', high, low]);
+        4
+    } else {
+        *buffer = [b'\\', b'u', b'{', high, low, b'}'];
+        6
+    };
+    std::str::from_utf8(&buffer[..len]).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sanitize(mode: ControlChars, pieces: &[&str]) -> String {
+        let mut sanitizer = Sanitizer::new(mode);
+        let mut sanitized = String::new();
+        for piece in pieces {
+            sanitizer.sanitize(piece, |run| sanitized.push_str(run));
+        }
+        sanitized
+    }
+
+    #[test]
+    fn test_strip() {
+        let strip = |text| ControlChars::Strip.apply(text).into_owned();
+
+        assert_eq!(strip("\x1b[1;31mError\x1b[0m: bad"), "Error: bad");
+        assert_eq!(strip("\x1b]0;title\x07Hello"), "Hello");
+        assert_eq!(strip("\x1b]8;;https://example.com\x1b\\link"), "link");
+        assert_eq!(strip("a\rb\x08c\x7fd\x1b7e"), "abcde");
+        assert_eq!(strip("tab\tnew\nline\0"), "tab\tnew\nline\0");
+        assert_eq!(strip("\x1b[31\u{1F601}"), "\u{1F601}");
+        assert_eq!(strip("\x1b[31\rm"), "m");
+        assert_eq!(strip("trailing\x1b["), "trailing");
+    }
+
+    #[test]
+    fn test_c1_controls() {
+        let strip = |text| ControlChars::Strip.apply(text).into_owned();
+        let escape = |text| ControlChars::Escape.apply(text).into_owned();
+
+        assert_eq!(strip("a\u{85}b\u{80}c\u{9f}"), "abc");
+        assert_eq!(strip("\u{9b}1;31mred\u{9b}0m"), "red");
+        assert_eq!(strip("\u{9d}0;title\u{9c}x"), "x");
+        assert_eq!(strip("\u{9d}0;title\x07y"), "y");
+        assert_eq!(escape("\u{9b}31m\u{85}"), "\\u{9b}31m\\u{85}");
+        assert_eq!(
+            sanitize(ControlChars::Strip, &["a\u{9b}", "31", "mb"]),
+            "ab"
+        );
+    }
+
+    #[test]
+    fn test_markers_end_sequences() {
+        assert_eq!(
+            sanitize(ControlChars::Strip, &["a\x1b]0;", "\u{FDD0}\u{FDE1}\x07b"]),
+            "a\u{FDD0}\u{FDE1}b"
+        );
+        assert_eq!(
+            sanitize(ControlChars::Strip, &["\u{9b}1", "\u{FDD2}m"]),
+            "\u{FDD2}m"
+        );
+    }
+
+    #[test]
+    fn test_strip_across_pieces() {
+        assert_eq!(
+            sanitize(
+                ControlChars::Strip,
+                &["a\x1b", "[", "31", "mb\x1b]0;", "t", "\x07c"]
+            ),
+            "abc"
+        );
+    }
+
+    #[test]
+    fn test_escape() {
+        let escape = |text| ControlChars::Escape.apply(text).into_owned();
+
+        assert_eq!(escape("\x1b[31mred\x1b[0m"), "\\x1b[31mred\\x1b[0m");
+        assert_eq!(escape("a\rb\x7f\t\n"), "a\\x0db\\x7f\t\n");
+    }
+
+    #[test]
+    fn test_utf8_is_untouched() {
+        for mode in [
+            ControlChars::Keep,
+            ControlChars::Strip,
+            ControlChars::Escape,
+        ] {
+            let text = "\u{1F601} caf\u{e9} \u{65e5}\u{672c} \u{a0}\u{ff}";
+            assert!(matches!(mode.apply(text), Cow::Borrowed(_)));
+            assert_eq!(sanitize(mode, &[text]), text);
+        }
+    }
+}
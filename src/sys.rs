@@ -1,6 +1,7 @@
 #![allow(non_camel_case_types)]
 #![allow(dead_code)]
 
+#[cfg(target_vendor = "apple")]
 use std::{ffi::c_void, os::raw::c_char};
 
 #[repr(C)]
@@ -19,15 +20,37 @@ pub const OS_LOG_TYPE_ERROR: os_log_type_t = 16;
 pub const OS_LOG_TYPE_FAULT: os_log_type_t = 17;
 
 // Provided by the OS.
+#[cfg(target_vendor = "apple")]
 extern "C" {
     pub fn os_log_create(subsystem: *const c_char, category: *const c_char) -> os_log_t;
+    pub fn os_retain(object: *mut c_void) -> *mut c_void;
     pub fn os_release(object: *mut c_void);
     pub fn os_log_type_enabled(log: os_log_t, level: os_log_type_t) -> bool;
+
+    // What the os_log_* macros expand to. `format` must be a constant in the
+    // image identified by `dso`, and `buf` is built by `crate::encoder`.
+    pub fn _os_log_impl(
+        dso: *mut c_void,
+        log: os_log_t,
+        log_type: os_log_type_t,
+        format: *const c_char,
+        buf: *const u8,
+        size: u32,
+    );
+
+    // Synthesized by the linker for each image.
+    pub static __dso_handle: u8;
+
+    // OS_LOG_DEFAULT and OS_LOG_DISABLED are the addresses of these.
+    pub static _os_log_default: os_log_s;
+    pub static _os_log_disabled: os_log_s;
 }
 
 // Wrappers defined in wrapper.c because most of the os_log_* APIs are macros.
+#[cfg(all(target_vendor = "apple", not(feature = "rust-encoder")))]
 extern "C" {
     pub fn wrapped_get_default_log() -> os_log_t;
+    pub fn wrapped_get_disabled_log() -> os_log_t;
     pub fn wrapped_os_log_with_type(log: os_log_t, log_type: os_log_type_t, message: *const c_char);
     pub fn wrapped_os_log_debug(log: os_log_t, message: *const c_char);
     pub fn wrapped_os_log_info(log: os_log_t, message: *const c_char);
@@ -36,7 +59,17 @@ extern "C" {
     pub fn wrapped_os_log_fault(log: os_log_t, message: *const c_char);
 }
 
-#[cfg(test)]
+#[cfg(target_vendor = "apple")]
+pub fn default_log() -> os_log_t {
+    std::ptr::addr_of!(_os_log_default) as os_log_t
+}
+
+#[cfg(target_vendor = "apple")]
+pub fn disabled_log() -> os_log_t {
+    std::ptr::addr_of!(_os_log_disabled) as os_log_t
+}
+
+#[cfg(all(test, target_vendor = "apple", not(feature = "rust-encoder")))]
 mod tests {
     use super::*;
     use std::ffi::CString;
@@ -53,6 +86,19 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_retain_and_release() {
+        let subsystem = CString::new("com.example.test").unwrap();
+        let category = CString::new("category").unwrap();
+        let log = unsafe { os_log_create(subsystem.as_ptr(), category.as_ptr()) };
+
+        unsafe {
+            assert_eq!(os_retain(log as *mut _), log as *mut _);
+            os_release(log as *mut _);
+            os_release(log as *mut _);
+        }
+    }
+
     #[test]
     fn test_output_to_default_log() {
         let message = CString::new("Hello!").unwrap();
@@ -92,6 +138,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_wrapped_logs_match_statics() {
+        unsafe {
+            assert_eq!(wrapped_get_default_log(), default_log());
+            assert_eq!(wrapped_get_disabled_log(), disabled_log());
+        }
+    }
+
+    #[test]
+    fn test_disabled_log() {
+        let message = CString::new("Hello!").unwrap();
+
+        unsafe {
+            let log = wrapped_get_disabled_log();
+            assert!(!log.is_null());
+            assert!(!os_log_type_enabled(log, OS_LOG_TYPE_FAULT));
+            wrapped_os_log_fault(log, message.as_ptr());
+        }
+    }
+
     #[test]
     fn test_output_to_custom_log() {
         let subsystem = CString::new("com.example.test").unwrap();
@@ -0,0 +1,145 @@
+//! Templates applied to `log` records by [`OsLogger`](crate::OsLogger).
+
+use crate::placeholder::{self, Segment};
+use log::Record;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Field {
+    Module,
+    File,
+    Line,
+    Target,
+    Thread,
+    ThreadId,
+    Message,
+}
+
+/// A message template where `{module}`, `{file}`, `{line}`, `{target}`,
+/// `{thread}`, `{thread_id}` and `{message}` are substituted, and `{{` and
+/// `}}` produce literal braces. Missing values render as `-`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Template {
+    segments: Vec<Segment<Field>>,
+}
+
+impl Template {
+    pub(crate) fn parse(template: &str) -> Self {
+        let segments = placeholder::parse(
+            template,
+            &[
+                ("{module}", Field::Module),
+                ("{file}", Field::File),
+                ("{line}", Field::Line),
+                ("{target}", Field::Target),
+                ("{thread}", Field::Thread),
+                ("{thread_id}", Field::ThreadId),
+                ("{message}", Field::Message),
+            ],
+        );
+
+        Self { segments }
+    }
+
+    /// Returns the message for `record`, formatted lazily.
+    pub(crate) fn render<'a>(&'a self, record: &'a Record<'a>) -> Rendered<'a> {
+        Rendered {
+            template: self,
+            record,
+        }
+    }
+}
+
+pub(crate) struct Rendered<'a> {
+    template: &'a Template,
+    record: &'a Record<'a>,
+}
+
+impl fmt::Display for Rendered<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let record = self.record;
+
+        for segment in &self.template.segments {
+            let field = match segment {
+                Segment::Text(text) => {
+                    f.write_str(text)?;
+                    continue;
+                }
+                Segment::Placeholder(field) => field,
+            };
+
+            match field {
+                Field::Module => f.write_str(record.module_path().unwrap_or("-"))?,
+                Field::File => f.write_str(record.file().unwrap_or("-"))?,
+                Field::Line => match record.line() {
+                    Some(line) => write!(f, "{}", line)?,
+                    None => f.write_str("-")?,
+                },
+                Field::Target => f.write_str(record.target())?,
+                Field::Thread => f.write_str(std::thread::current().name().unwrap_or("-"))?,
+                Field::ThreadId => write!(f, "{}", crate::thread::id())?,
+                Field::Message => f.write_fmt(*record.args())?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Template::parse("{{{module}}}:{line} {unknown} {message}").segments,
+            [
+                Segment::Text(String::from("{")),
+                Segment::Placeholder(Field::Module),
+                Segment::Text(String::from("}:")),
+                Segment::Placeholder(Field::Line),
+                Segment::Text(String::from(" {unknown} ")),
+                Segment::Placeholder(Field::Message),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let render = || {
+            let template = Template::parse(
+                "[{module}:{file}:{line}] {target} {thread}#{thread_id}: {message}",
+            );
+            let record = Record::builder()
+                .module_path(Some("app::sync"))
+                .file(Some("src/sync.rs"))
+                .line(Some(42))
+                .target("Sync")
+                .args(format_args!("3 items"))
+                .build();
+
+            (template.render(&record).to_string(), crate::thread::id())
+        };
+
+        let (rendered, id) = std::thread::Builder::new()
+            .name(String::from("worker"))
+            .spawn(render)
+            .unwrap()
+            .join()
+            .unwrap();
+
+        assert_eq!(
+            rendered,
+            format!("[app::sync:src/sync.rs:42] Sync worker#{}: 3 items", id)
+        );
+    }
+
+    #[test]
+    fn test_render_missing_values() {
+        let template = Template::parse("{module} {file} {line} {message}");
+        let record = Record::builder().args(format_args!("Hello")).build();
+
+        assert_eq!(template.render(&record).to_string(), "- - - Hello");
+    }
+}
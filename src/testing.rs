@@ -0,0 +1,265 @@
+//! Helpers for checking what was logged, without needing Apple's log tools.
+//!
+//! ```
+//! use oslog::testing::CaptureBackend;
+//! use oslog::Level;
+//!
+//! let capture = CaptureBackend::new();
+//! let log = capture.log("com.example.test", "Settings");
+//! log.info("Loaded");
+//!
+//! capture.assert_logged(Level::Info, "Loaded");
+//! ```
+
+use crate::backend::{Backend, Handle};
+use crate::Error;
+use crate::{Level, OsLog};
+use std::borrow::Cow;
+use std::ffi::CStr;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// A single message recorded by a [`CaptureBackend`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub subsystem: String,
+    pub category: String,
+    pub level: Level,
+    /// The message exactly as it was handed to the backend, without the
+    /// trailing nul.
+    pub message: Vec<u8>,
+}
+
+impl Entry {
+    pub fn message_str(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.message)
+    }
+}
+
+/// Records every message written through it. Clones share the same entries.
+#[derive(Clone, Default)]
+pub struct CaptureBackend {
+    entries: Arc<Mutex<Vec<Entry>>>,
+}
+
+impl CaptureBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a log which writes to this backend.
+    pub fn log(&self, subsystem: &str, category: &str) -> OsLog {
+        OsLog::with_backend(self, subsystem, category)
+    }
+
+    /// Returns a copy of everything recorded so far.
+    pub fn entries(&self) -> Vec<Entry> {
+        self.lock().clone()
+    }
+
+    /// Returns everything recorded so far and clears the backend.
+    pub fn take(&self) -> Vec<Entry> {
+        std::mem::take(&mut *self.lock())
+    }
+
+    /// Returns the messages recorded so far, decoded lossily.
+    pub fn messages(&self) -> Vec<String> {
+        self.lock()
+            .iter()
+            .map(|entry| entry.message_str().into_owned())
+            .collect()
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Returns the entries matching `predicate`.
+    pub fn filter<P>(&self, mut predicate: P) -> Vec<Entry>
+    where
+        P: FnMut(&Entry) -> bool,
+    {
+        self.lock()
+            .iter()
+            .filter(|e| predicate(e))
+            .cloned()
+            .collect()
+    }
+
+    pub fn for_category(&self, category: &str) -> Vec<Entry> {
+        self.filter(|e| e.category == category)
+    }
+
+    pub fn for_level(&self, level: Level) -> Vec<Entry> {
+        self.filter(|e| e.level == level)
+    }
+
+    /// Panics unless a message equal to `message` was logged at `level`.
+    #[track_caller]
+    pub fn assert_logged(&self, level: Level, message: &str) {
+        let entries = self.lock();
+        assert!(
+            entries
+                .iter()
+                .any(|e| e.level == level && e.message == message.as_bytes()),
+            "expected {:?} message {:?}, captured: {:#?}",
+            level,
+            message,
+            *entries
+        );
+    }
+
+    /// Panics if a message equal to `message` was logged at any level.
+    #[track_caller]
+    pub fn assert_not_logged(&self, message: &str) {
+        let entries = self.lock();
+        assert!(
+            !entries.iter().any(|e| e.message == message.as_bytes()),
+            "unexpected message {:?}, captured: {:#?}",
+            message,
+            *entries
+        );
+    }
+
+    /// Panics unless exactly `count` messages have been recorded.
+    #[track_caller]
+    pub fn assert_count(&self, count: usize) {
+        let entries = self.lock();
+        assert_eq!(entries.len(), count, "captured: {:#?}", *entries);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<Entry>> {
+        // A panicking assertion elsewhere shouldn't hide what was captured.
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Backend for CaptureBackend {
+    fn create(&self, subsystem: &CStr, category: &CStr) -> Result<Box<dyn Handle>, Error> {
+        Ok(Box::new(CaptureHandle {
+            subsystem: subsystem.to_string_lossy().into_owned(),
+            category: category.to_string_lossy().into_owned(),
+            backend: self.clone(),
+        }))
+    }
+
+    fn global(&self) -> Result<Box<dyn Handle>, Error> {
+        self.create(c"", c"")
+    }
+}
+
+#[derive(Clone)]
+struct CaptureHandle {
+    subsystem: String,
+    category: String,
+    backend: CaptureBackend,
+}
+
+impl Handle for CaptureHandle {
+    fn clone_handle(&self) -> Box<dyn Handle> {
+        Box::new(self.clone())
+    }
+
+    fn log(&self, level: Level, message: &CStr) {
+        self.backend.lock().push(Entry {
+            subsystem: self.subsystem.clone(),
+            category: self.category.clone(),
+            level,
+            message: message.to_bytes().to_vec(),
+        });
+    }
+
+    fn level_is_enabled(&self, _level: Level) -> bool {
+        true
+    }
+}
+
+/// Discards every message without allocating, with every level enabled, e.g.
+/// for measuring what logging costs.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NullBackend;
+
+impl NullBackend {
+    /// Creates a log which writes to this backend.
+    pub fn log(&self, subsystem: &str, category: &str) -> OsLog {
+        OsLog::with_backend(self, subsystem, category)
+    }
+}
+
+impl Backend for NullBackend {
+    fn create(&self, _subsystem: &CStr, _category: &CStr) -> Result<Box<dyn Handle>, Error> {
+        Ok(Box::new(NullHandle))
+    }
+
+    fn global(&self) -> Result<Box<dyn Handle>, Error> {
+        Ok(Box::new(NullHandle))
+    }
+}
+
+#[derive(Clone)]
+struct NullHandle;
+
+impl Handle for NullHandle {
+    fn clone_handle(&self) -> Box<dyn Handle> {
+        Box::new(self.clone())
+    }
+
+    fn log(&self, _level: Level, _message: &CStr) {}
+
+    fn level_is_enabled(&self, _level: Level) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_subsystem_and_category() {
+        let capture = CaptureBackend::new();
+        capture.log("com.example.test", "Settings").error("Failed");
+
+        assert_eq!(
+            capture.entries(),
+            vec![Entry {
+                subsystem: "com.example.test".into(),
+                category: "Settings".into(),
+                level: Level::Error,
+                message: b"Failed".to_vec(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_filters() {
+        let capture = CaptureBackend::new();
+        let settings = capture.log("com.example.test", "Settings");
+        let database = capture.log("com.example.test", "Database");
+
+        settings.debug("One");
+        database.debug("Two");
+        database.fault("Three");
+
+        assert_eq!(capture.for_category("Database").len(), 2);
+        assert_eq!(capture.for_level(Level::Debug).len(), 2);
+        capture.assert_logged(Level::Fault, "Three");
+        capture.assert_not_logged("Four");
+    }
+
+    #[test]
+    fn test_take_clears() {
+        let capture = CaptureBackend::new();
+        capture.log("com.example.test", "category").info("Hi");
+
+        assert_eq!(capture.take().len(), 1);
+        capture.assert_count(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected Info message")]
+    fn test_assert_logged_fails() {
+        let capture = CaptureBackend::new();
+        capture.log("com.example.test", "category").debug("Hi");
+        capture.assert_logged(Level::Info, "Hi");
+    }
+}
@@ -0,0 +1,38 @@
+//! Details of the current thread for log lines.
+
+thread_local! {
+    static ID: u64 = parse_id();
+}
+
+/// The numeric id of the current thread, as shown in its `Debug` output.
+pub(crate) fn id() -> u64 {
+    ID.try_with(|id| *id).unwrap_or_else(|_| parse_id())
+}
+
+/// `ThreadId::as_u64` isn't stable, but its `Debug` output is
+/// `ThreadId(<id>)`.
+fn parse_id() -> u64 {
+    let id = format!("{:?}", std::thread::current().id());
+    id.trim_start_matches("ThreadId(")
+        .trim_end_matches(')')
+        .parse()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_id() {
+        let main = id();
+        assert_eq!(id(), main);
+        assert_eq!(
+            format!("{:?}", std::thread::current().id()),
+            format!("ThreadId({})", main)
+        );
+
+        let other = std::thread::spawn(id).join().unwrap();
+        assert_ne!(other, main);
+    }
+}
@@ -4,6 +4,10 @@ os_log_t wrapped_get_default_log() {
     return OS_LOG_DEFAULT;
 }
 
+os_log_t wrapped_get_disabled_log() {
+    return OS_LOG_DISABLED;
+}
+
 void wrapped_os_log_with_type(os_log_t log, os_log_type_t type, const char* message) {
     os_log_with_type(log, type, "%{public}s", message);
 }